@@ -11,9 +11,9 @@ fn main() {
 
     let input = buf.as_str();
     let tokens = algo::lexer::lex(input);
-    let exprs = algo::parser::parse(tokens).unwrap_or_else(|errs| handle_errors(input, errs));
-    let typed_ast =
-        algo::types::type_exprs(exprs).unwrap_or_else(|errs| handle_errors(input, errs));
+    let program = algo::parser::parse(tokens).unwrap_or_else(|errs| handle_errors(input, errs));
+    let typed_ast = algo::types::type_exprs(program.into_items())
+        .unwrap_or_else(|errs| handle_errors(input, errs));
 
     let end = Instant::now();
     let compile_time = end - start;
@@ -23,8 +23,10 @@ fn main() {
 }
 
 fn handle_errors(src: &str, errs: Vec<algo::Error>) -> ! {
+    let config = algo::ReportConfig::from_env();
+
     for err in errs {
-        err.generate_report()
+        err.generate_report_with(src, config)
             .eprint(ariadne::Source::from(src))
             .unwrap();
     }
@@ -1,15 +1,30 @@
+use crate::{
+    strings::{get_intern_str, Interner},
+    types::IntWidth,
+    Error,
+};
 use intaglio::Symbol;
 use logos::{Lexer, Logos, Span};
 
-#[derive(Logos, Debug, Clone, PartialEq, Eq, Hash)]
+/// State the lexer callbacks share: errors raised so far, and the interner symbols are added to.
+#[derive(Debug, Default)]
+pub struct LexerExtras {
+    errors: Vec<Error>,
+    interner: Interner,
+}
+
+#[derive(Logos, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[logos(extras = LexerExtras)]
 pub enum TokenKind {
-    #[regex(r"#\[.+\]", |lex| lex.slice().parse())]
-    Preprocess(String),
+    #[regex(r"#\[.+\]", trim_and_cache)]
+    Preprocess(Symbol),
 
     #[token("this")]
     This,
     #[token("::")]
     Path,
+    #[token(".")]
+    Dot,
 
     #[token(";")]
     Terminator,
@@ -33,6 +48,10 @@ pub enum TokenKind {
     VarDef,
     #[token("type")]
     TypeDef,
+    #[token("if")]
+    If,
+    #[token("else")]
+    Else,
 
     #[token("()")]
     TypeUnit,
@@ -40,8 +59,24 @@ pub enum TokenKind {
     TypeInt,
     #[token("UInt")]
     TypeUInt,
+    #[token("i8", |_| IntWidth::W8)]
+    #[token("i16", |_| IntWidth::W16)]
+    #[token("i32", |_| IntWidth::W32)]
+    #[token("i64", |_| IntWidth::W64)]
+    TypeIntN(IntWidth),
+    #[token("u8", |_| IntWidth::W8)]
+    #[token("u16", |_| IntWidth::W16)]
+    #[token("u32", |_| IntWidth::W32)]
+    #[token("u64", |_| IntWidth::W64)]
+    TypeUIntN(IntWidth),
     #[token("Bool")]
     TypeBool,
+    #[token("Float")]
+    TypeFloat,
+    #[token("Str")]
+    TypeStr,
+    #[token("Char")]
+    TypeChar,
 
     #[token("+")]
     Add,
@@ -92,14 +127,29 @@ pub enum TokenKind {
     #[token("=>")]
     Flow,
 
-    #[regex(r"!?\d+I?", lex_integer, priority = 3)]
+    #[regex(r"!?\d[\d_]*I?", lex_integer, priority = 3)]
+    #[regex(r"!?0x[\da-fA-F_]*I?", lex_integer)]
+    #[regex(r"!?0o[0-7_]*I?", lex_integer)]
+    #[regex(r"!?0b[01_]*I?", lex_integer)]
+    // Otherwise `_1` would lex as a symbol, hiding the misplaced separator.
+    #[regex(r"_[\d_]*\d[\d_]*", lex_integer)]
     Integer(isize),
-    #[regex(r"\d+U?", lex_uinteger, priority = 2)]
+    #[regex(r"\d[\d_]*U?", lex_uinteger, priority = 2)]
+    #[regex(r"0x[\da-fA-F_]*U", lex_uinteger)]
+    #[regex(r"0o[0-7_]*U", lex_uinteger)]
+    #[regex(r"0b[01_]*U", lex_uinteger)]
     UInteger(usize),
+    /// An `f64` literal, stored as its bit pattern so tokens stay `Eq` and `Hash`.
+    #[regex(r"\d[\d_]*\.\d[\d_]*([eE][+-]?\d[\d_]*)?", lex_float)]
+    #[regex(r"\d[\d_]*[eE][+-]?\d[\d_]*", lex_float)]
+    Float(u64),
     #[regex(r"true|false", |lex| lex.slice().parse())]
     Boolean(bool),
-    #[regex(r#""[^"\\]*(?:\\.[^"\\]*)*""#, trim_and_cache)]
-    String(Symbol),
+    // The closing quote is optional so unterminated literals reach the callback and get reported.
+    #[regex(r#""([^"\\]|\\.)*"?"#, lex_string)]
+    StringLiteral(Symbol),
+    #[regex(r"'([^'\\]|\\.)*'?", lex_char)]
+    CharLiteral(char),
     #[regex(r"[A-Za-z_][\w]*", trim_and_cache)]
     Symbol(Symbol),
 
@@ -114,6 +164,33 @@ pub enum TokenKind {
     Error,
 }
 
+// Tokens are copied freely during lookahead and error reporting, so keep every payload `Copy`.
+const _: fn() = || {
+    fn assert_copy<T: Copy>() {}
+    assert_copy::<TokenKind>();
+};
+
+impl TokenKind {
+    /// Whether this token can begin an expression.
+    pub const fn starts_expr(&self) -> bool {
+        matches!(
+            self,
+            Self::TypeUnit
+                | Self::Integer(_)
+                | Self::UInteger(_)
+                | Self::Float(_)
+                | Self::Boolean(_)
+                | Self::StringLiteral(_)
+                | Self::CharLiteral(_)
+                | Self::Symbol(_)
+                | Self::If
+                | Self::GroupOpen
+                | Self::ArrayOpen
+                | Self::BlockOpen
+        )
+    }
+}
+
 fn lex_integer(lexer: &mut Lexer<TokenKind>) -> Option<isize> {
     let slice = lexer.slice();
 
@@ -124,10 +201,16 @@ fn lex_integer(lexer: &mut Lexer<TokenKind>) -> Option<isize> {
     };
     let front_offset = if slice.starts_with('!') { 1 } else { 0 };
 
-    slice[front_offset..back_offset]
-        .parse::<isize>()
+    let (radix, digits) = literal_digits(lexer, front_offset, back_offset)?;
+    let int = isize::from_str_radix(&digits, radix)
         .map(|int| if front_offset > 0 { !int } else { int })
-        .ok()
+        .ok();
+
+    if int.is_none() {
+        push_out_of_range(lexer);
+    }
+
+    int
 }
 
 fn lex_uinteger(lexer: &mut Lexer<TokenKind>) -> Option<usize> {
@@ -139,7 +222,220 @@ fn lex_uinteger(lexer: &mut Lexer<TokenKind>) -> Option<usize> {
         slice.len()
     };
 
-    slice[0..back_offset].parse::<usize>().ok()
+    let (radix, digits) = literal_digits(lexer, 0, back_offset)?;
+    let uint = usize::from_str_radix(&digits, radix).ok();
+
+    if uint.is_none() {
+        push_out_of_range(lexer);
+    }
+
+    uint
+}
+
+fn lex_string(lexer: &mut Lexer<TokenKind>) -> Option<Symbol> {
+    let contents = quoted_contents(lexer, '"', "unterminated string literal")?;
+
+    // Only literals containing escapes need a new allocation.
+    if contents.contains('\\') {
+        let unescaped = unescape(lexer, contents)?;
+        cache(lexer, &unescaped)
+    } else {
+        cache(lexer, contents)
+    }
+}
+
+fn lex_char(lexer: &mut Lexer<TokenKind>) -> Option<char> {
+    let contents = quoted_contents(lexer, '\'', "unterminated character literal")?;
+    let unescaped = unescape(lexer, contents)?;
+
+    let mut chars = unescaped.chars();
+    if let (Some(char), None) = (chars.next(), chars.next()) {
+        Some(char)
+    } else {
+        lexer.extras.errors.push(Error::general(
+            lexer.span(),
+            "character literal must contain exactly one character",
+            Some("lexer::lex_char"),
+        ));
+
+        None
+    }
+}
+
+/// Strips the surrounding quotes from the current literal, reporting the opening quote if the
+/// literal runs to the end of input without being closed.
+fn quoted_contents<'a>(
+    lexer: &mut Lexer<'a, TokenKind>,
+    quote: char,
+    unterminated_msg: &str,
+) -> Option<&'a str> {
+    let slice = lexer.slice();
+
+    // The regex reads `\"` as an escape, so a trailing quote only closes the literal if an even
+    // number of backslashes precedes it.
+    let closed = slice.len() > 1
+        && slice.strip_suffix(quote).is_some_and(|rest| {
+            rest.chars().rev().take_while(|char| *char == '\\').count() % 2 == 0
+        });
+
+    if closed {
+        Some(&slice[1..slice.len() - 1])
+    } else {
+        let start = lexer.span().start;
+        lexer.extras.errors.push(Error::general(
+            start..start + 1,
+            unterminated_msg,
+            Some("lexer::quoted_contents"),
+        ));
+
+        None
+    }
+}
+
+/// Resolves the escape sequences in a literal's `contents`, reporting the first invalid one.
+fn unescape(lexer: &mut Lexer<TokenKind>, contents: &str) -> Option<String> {
+    // Offset of `contents` in the source, past the opening quote.
+    let offset = lexer.span().start + 1;
+    let mut unescaped = String::with_capacity(contents.len());
+    let mut chars = contents.char_indices().peekable();
+
+    while let Some((backslash, char)) = chars.next() {
+        if char != '\\' {
+            unescaped.push(char);
+            continue;
+        }
+
+        let escaped = match chars.next() {
+            Some((_, 'n')) => Ok('\n'),
+            Some((_, 't')) => Ok('\t'),
+            Some((_, escape @ ('\\' | '"' | '\''))) => Ok(escape),
+            Some((index, 'u')) => unescape_unicode(&mut chars).map_err(|bad| bad.unwrap_or(index)),
+            Some((index, _)) => Err(index),
+            // A backslash ending the contents has nothing to escape.
+            None => Err(backslash),
+        };
+
+        match escaped {
+            Ok(escaped) => unescaped.push(escaped),
+            Err(bad) => {
+                let bad_len = contents[bad..].chars().next().map_or(1, char::len_utf8);
+                lexer.extras.errors.push(Error::general(
+                    offset + bad..offset + bad + bad_len,
+                    "invalid escape sequence",
+                    Some("lexer::unescape"),
+                ));
+
+                return None;
+            }
+        }
+    }
+
+    Some(unescaped)
+}
+
+/// Parses the `{XXXX}` following a `\u` escape. On failure, returns the index of the offending
+/// character, or `None` to blame the escape itself.
+fn unescape_unicode(
+    chars: &mut std::iter::Peekable<std::str::CharIndices>,
+) -> Result<char, Option<usize>> {
+    match chars.next() {
+        Some((_, '{')) => {}
+        Some((index, _)) => return Err(Some(index)),
+        None => return Err(None),
+    }
+
+    let mut code = 0u32;
+    let mut digits = 0;
+    loop {
+        match chars.next() {
+            Some((_, '}')) if digits > 0 => break,
+            Some((index, char)) => match char.to_digit(16) {
+                Some(digit) if digits < 6 => {
+                    code = code * 16 + digit;
+                    digits += 1;
+                }
+                _ => return Err(Some(index)),
+            },
+            None => return Err(None),
+        }
+    }
+
+    char::from_u32(code).ok_or(None)
+}
+
+fn lex_float(lexer: &mut Lexer<TokenKind>) -> Option<u64> {
+    let slice = lexer.slice();
+
+    // Each run of digits (integer part, fraction, exponent) follows the integer separator rules.
+    let mut offset = 0;
+    for digits in slice.split(['.', 'e', 'E', '+', '-']) {
+        strip_separators(lexer, digits, offset)?;
+        // Every delimiter between runs is a single byte.
+        offset += digits.len() + 1;
+    }
+
+    slice.replace('_', "").parse::<f64>().ok().map(f64::to_bits)
+}
+
+fn push_out_of_range(lexer: &mut Lexer<TokenKind>) {
+    lexer.extras.errors.push(Error::general(
+        lexer.span(),
+        "integer literal out of range",
+        Some("lexer::lex_integer"),
+    ));
+}
+
+/// Splits any `0x`, `0o`, or `0b` prefix off the digits between `front` and `back` in the current
+/// token, returning the radix alongside the digits with separators removed.
+fn literal_digits(
+    lexer: &mut Lexer<TokenKind>,
+    front: usize,
+    back: usize,
+) -> Option<(u32, String)> {
+    let slice = lexer.slice();
+    let (radix, front) = match slice.get(front..front + 2) {
+        Some("0x") => (16, front + 2),
+        Some("0o") => (8, front + 2),
+        Some("0b") => (2, front + 2),
+        _ => (10, front),
+    };
+
+    let digits = strip_separators(lexer, &slice[front..back], front)?;
+    if digits.is_empty() {
+        lexer.extras.errors.push(Error::general(
+            lexer.span(),
+            "integer literal has no digits after its radix prefix",
+            Some("lexer::literal_digits"),
+        ));
+
+        return None;
+    }
+
+    Some((radix, digits))
+}
+
+/// Removes `_` digit separators, reporting the first one that isn't placed between two digits.
+///
+/// `offset` is where `digits` starts within the current token.
+fn strip_separators(lexer: &mut Lexer<TokenKind>, digits: &str, offset: usize) -> Option<String> {
+    let misplaced = digits
+        .starts_with('_')
+        .then_some(0)
+        .or_else(|| digits.find("__").map(|index| index + 1))
+        .or_else(|| digits.ends_with('_').then(|| digits.len() - 1));
+
+    if let Some(index) = misplaced {
+        let start = lexer.span().start + offset + index;
+        lexer.extras.errors.push(Error::general(
+            start..start + 1,
+            "digit separator `_` must be between two digits",
+            Some("lexer::strip_separators"),
+        ));
+
+        None
+    } else {
+        Some(digits.replace('_', ""))
+    }
 }
 
 impl core::fmt::Display for TokenKind {
@@ -155,10 +451,15 @@ impl core::fmt::Display for TokenKind {
             TokenKind::ArrayClose => "]",
             TokenKind::VarDef => "var",
             TokenKind::TypeDef => "type",
+            TokenKind::If => "if",
+            TokenKind::Else => "else",
             TokenKind::TypeUnit => "()",
             TokenKind::TypeInt => "Int",
             TokenKind::TypeUInt => "UInt",
             TokenKind::TypeBool => "Bool",
+            TokenKind::TypeFloat => "Float",
+            TokenKind::TypeStr => "Str",
+            TokenKind::TypeChar => "Char",
             TokenKind::Add => "+",
             TokenKind::Sub => "-",
             TokenKind::Mul => "*",
@@ -181,32 +482,93 @@ impl core::fmt::Display for TokenKind {
             TokenKind::And => "&&",
             TokenKind::Assign => ":",
             TokenKind::Flow => "=>",
-
-            _ => return Err(std::fmt::Error),
+            TokenKind::This => "this",
+            TokenKind::Path => "::",
+            TokenKind::Dot => ".",
+            TokenKind::Clow => "?>",
+            TokenKind::Cerm => "??",
+            TokenKind::Error => "<error>",
+
+            TokenKind::Integer(int) => return write!(f, "{int}"),
+            TokenKind::UInteger(uint) => return write!(f, "{uint}U"),
+            TokenKind::TypeIntN(width) => {
+                return write!(f, "i{}", width.bits().unwrap_or_default())
+            }
+            TokenKind::TypeUIntN(width) => {
+                return write!(f, "u{}", width.bits().unwrap_or_default())
+            }
+            TokenKind::Float(bits) => return write!(f, "{:?}", f64::from_bits(*bits)),
+            TokenKind::Boolean(bool) => return write!(f, "{bool}"),
+            TokenKind::StringLiteral(symbol) => return write!(f, "{:?}", get_intern_str(*symbol)),
+            TokenKind::CharLiteral(char) => return write!(f, "{char:?}"),
+            TokenKind::Preprocess(symbol) | TokenKind::Symbol(symbol) => {
+                return f.write_str(&get_intern_str(*symbol))
+            }
+            TokenKind::EnvVar(symbol) => return write!(f, "$\"{}\"", get_intern_str(*symbol)),
+            TokenKind::EnvCmd(symbol) => return write!(f, "${}", get_intern_str(*symbol)),
         };
 
         f.write_str(display_str)
     }
 }
 
-fn trim_and_cache(lexer: &mut Lexer<TokenKind>) -> Symbol {
-    crate::strings::intern_str(
-        lexer
-            .slice()
-            .trim_start_matches('$')
-            .trim_start_matches('"')
-            .trim_end_matches('"'),
-    )
+/// Serializes as the token's display name, e.g. `"=>"`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for TokenKind {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+fn trim_and_cache(lexer: &mut Lexer<TokenKind>) -> Option<Symbol> {
+    let slice = lexer
+        .slice()
+        .trim_start_matches('$')
+        .trim_start_matches('"')
+        .trim_end_matches('"');
+
+    cache(lexer, slice)
+}
+
+fn cache(lexer: &mut Lexer<TokenKind>, string: &str) -> Option<Symbol> {
+    let symbol = lexer.extras.interner.intern(string);
+
+    if symbol.is_none() {
+        lexer.extras.errors.push(Error::general(
+            lexer.span(),
+            "too many unique symbols",
+            Some("lexer::trim_and_cache"),
+        ));
+    }
+
+    symbol
 }
 
 pub type Token = (TokenKind, Span);
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Tokens {
     tokens: Box<[Token]>,
+    errors: Box<[Error]>,
     index: usize,
 }
 
+impl Tokens {
+    /// Errors raised while lexing, for input that couldn't be represented as a token.
+    #[inline]
+    pub fn errors(&self) -> &[Error] {
+        &self.errors
+    }
+
+    /// The span from the start of the first token to the end of the last, or `0..0` if there are none.
+    pub fn span(&self) -> Span {
+        match (self.tokens.first(), self.tokens.last()) {
+            (Some((_, first)), Some((_, last))) => first.start..last.end,
+            _ => 0..0,
+        }
+    }
+}
+
 impl Iterator for Tokens {
     type Item = Token;
 
@@ -228,19 +590,61 @@ impl From<Tokens> for chumsky::Stream<'_, TokenKind, Span, Tokens> {
     }
 }
 
+/// Splits a float slice like `0.1` starting at `start` into two integer tokens around a `Dot`, or
+/// returns `None` if either half isn't a plain integer (e.g. it has an exponent).
+fn split_tuple_indices(slice: &str, start: usize) -> Option<[(TokenKind, Span); 3]> {
+    let (lhs, rhs) = slice.split_once('.')?;
+    let index = |digits: &str| digits.replace('_', "").parse().ok().map(TokenKind::Integer);
+    let dot = start + lhs.len();
+
+    Some([
+        (index(lhs)?, start..dot),
+        (TokenKind::Dot, dot..dot + 1),
+        (index(rhs)?, dot + 1..start + slice.len()),
+    ])
+}
+
+/// Lexes `input`, interning symbols in the shared string cache behind `interned!`.
 pub fn lex(input: &str) -> Tokens {
-    let mut tokens = TokenKind::lexer(input).spanned().collect::<Vec<Token>>();
-    tokens.shrink_to_fit();
+    crate::strings::with_shared_interner(|interner| lex_with(input, interner))
+}
+
+/// Lexes `input`, interning symbols in `interner` instead of the shared string cache.
+///
+/// Parsing and typing never intern, so a compilation lexed this way never writes to the shared
+/// cache.
+pub fn lex_with(input: &str, interner: &mut Interner) -> Tokens {
+    let extras = LexerExtras {
+        errors: Vec::new(),
+        interner: std::mem::take(interner),
+    };
+    let mut lexer = TokenKind::lexer_with_extras(input, extras);
+    let mut tokens = Vec::new();
+    while let Some(kind) = lexer.next() {
+        // Chained tuple indices like `tuple.0.1` lex as a float, so split them back apart.
+        if matches!(kind, TokenKind::Float(_)) && matches!(tokens.last(), Some((TokenKind::Dot, _)))
+        {
+            if let Some(indices) = split_tuple_indices(lexer.slice(), lexer.span().start) {
+                tokens.extend(indices);
+                continue;
+            }
+        }
+
+        tokens.push((kind, lexer.span()));
+    }
+
+    *interner = lexer.extras.interner;
 
     Tokens {
         tokens: tokens.into_boxed_slice(),
+        errors: lexer.extras.errors.into_boxed_slice(),
         index: 0,
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{lexer::TokenKind, tests::lex_and_eq};
+    use crate::{interned, lexer::TokenKind, tests::lex_and_eq};
 
     #[test]
     fn integer() {
@@ -266,4 +670,274 @@ mod tests {
     fn uinteger_u() {
         lex_and_eq("12345U", [TokenKind::UInteger(12345)].into_iter());
     }
+
+    #[test]
+    fn integer_separators() {
+        lex_and_eq("1_000", [TokenKind::Integer(1000)].into_iter());
+        lex_and_eq("!1_000I", [TokenKind::Integer(!1000)].into_iter());
+        lex_and_eq("1_000_000U", [TokenKind::UInteger(1_000_000)].into_iter());
+        lex_and_eq("0xFF_FF", [TokenKind::Integer(0xFFFF)].into_iter());
+    }
+
+    #[test]
+    fn integer_separators_misplaced() {
+        lex_and_eq("_1", [TokenKind::Error].into_iter());
+        lex_and_eq("1_", [TokenKind::Error].into_iter());
+        lex_and_eq("1__0", [TokenKind::Error].into_iter());
+        lex_and_eq("1_U", [TokenKind::Error].into_iter());
+    }
+
+    #[test]
+    fn separator_error_spans() {
+        let cases = [
+            ("_1", 0..1),
+            ("__1_000", 0..1),
+            ("1_", 1..2),
+            ("1__0", 2..3),
+            ("1_U", 1..2),
+            ("!1_I", 2..3),
+            ("1_.5", 1..2),
+            ("1.5_e3", 3..4),
+            ("2 + 1_000__0", 10..11),
+            ("0x_FF", 2..3),
+            ("0b1_", 3..4),
+        ];
+
+        for (input, span) in cases {
+            let tokens = crate::lexer::lex(input);
+
+            assert_eq!(tokens.errors().len(), 1, "errors for {input:?}");
+            assert_eq!(tokens.errors()[0].span(), &span, "span for {input:?}");
+        }
+    }
+
+    #[test]
+    fn radix_integers() {
+        lex_and_eq("0xFF", [TokenKind::Integer(255)].into_iter());
+        lex_and_eq("0b1010", [TokenKind::Integer(10)].into_iter());
+        lex_and_eq("0o17", [TokenKind::Integer(15)].into_iter());
+        lex_and_eq("!0xFFI", [TokenKind::Integer(!255)].into_iter());
+        lex_and_eq("0xffU", [TokenKind::UInteger(255)].into_iter());
+    }
+
+    #[test]
+    fn radix_integer_empty() {
+        for input in ["0x", "0o", "0bU"] {
+            let tokens = crate::lexer::lex(input);
+
+            assert_eq!(tokens.errors().len(), 1, "errors for {input:?}");
+            assert_eq!(tokens.errors()[0].span(), &(0..input.len()));
+        }
+    }
+
+    #[test]
+    fn radix_integer_out_of_range() {
+        let tokens = crate::lexer::lex("0x1_0000_0000_0000_0000");
+
+        assert_eq!(tokens.errors().len(), 1);
+        assert_eq!(tokens.errors()[0].span(), &(0..23));
+    }
+
+    #[test]
+    fn integer_out_of_range() {
+        let tokens = crate::lexer::lex("99999999999999999999999");
+
+        assert_eq!(tokens.errors().len(), 1);
+        assert_eq!(tokens.errors()[0].span(), &(0..23));
+    }
+
+    #[test]
+    fn shebang() {
+        // A leading shebang is just a comment line, so executable scripts lex cleanly.
+        lex_and_eq(
+            "#!/usr/bin/env algosh\n12345",
+            [TokenKind::Integer(12345)].into_iter(),
+        );
+    }
+
+    #[test]
+    fn shebang_mid_file() {
+        lex_and_eq(
+            "1 #! not a shebang\n2",
+            [TokenKind::Integer(1), TokenKind::Integer(2)].into_iter(),
+        );
+    }
+
+    #[test]
+    fn lookahead_copy() {
+        let mut tokens = crate::lexer::lex("a + 1").peekable();
+        let peeked = tokens.peek().map(|(kind, _)| *kind);
+
+        assert_eq!(peeked, tokens.next().map(|(kind, _)| kind));
+        assert_eq!(peeked, Some(TokenKind::Symbol(interned!("a"))));
+    }
+
+    #[test]
+    fn float() {
+        let float = |float: f64| TokenKind::Float(float.to_bits());
+
+        lex_and_eq("5.0", [float(5.0)].into_iter());
+        lex_and_eq("5e0", [float(5.0)].into_iter());
+        lex_and_eq("2.75", [float(2.75)].into_iter());
+        lex_and_eq("1e10", [float(1e10)].into_iter());
+        lex_and_eq("2.5e-3", [float(2.5e-3)].into_iter());
+        lex_and_eq("1_000.5", [float(1000.5)].into_iter());
+    }
+
+    #[test]
+    fn float_separators_misplaced() {
+        lex_and_eq("1_.5", [TokenKind::Error].into_iter());
+        lex_and_eq("1.5_e3", [TokenKind::Error].into_iter());
+    }
+
+    #[test]
+    fn chained_tuple_indices() {
+        let tokens = crate::lexer::lex("t.0.12 + 1.5").collect::<Vec<_>>();
+
+        assert_eq!(
+            tokens,
+            [
+                (TokenKind::Symbol(interned!("t")), 0..1),
+                (TokenKind::Dot, 1..2),
+                (TokenKind::Integer(0), 2..3),
+                (TokenKind::Dot, 3..4),
+                (TokenKind::Integer(12), 4..6),
+                (TokenKind::Add, 7..8),
+                (TokenKind::Float(1.5f64.to_bits()), 9..12),
+            ]
+        );
+    }
+
+    #[test]
+    fn fixed_width_types() {
+        use crate::types::IntWidth;
+
+        lex_and_eq("i8", [TokenKind::TypeIntN(IntWidth::W8)].into_iter());
+        lex_and_eq("u16", [TokenKind::TypeUIntN(IntWidth::W16)].into_iter());
+        lex_and_eq("u64", [TokenKind::TypeUIntN(IntWidth::W64)].into_iter());
+        lex_and_eq("i128", [TokenKind::Symbol(interned!("i128"))].into_iter());
+    }
+
+    #[test]
+    fn if_else_keywords() {
+        lex_and_eq(
+            "if else elsewhere",
+            [
+                TokenKind::If,
+                TokenKind::Else,
+                TokenKind::Symbol(interned!("elsewhere")),
+            ]
+            .into_iter(),
+        );
+    }
+
+    #[test]
+    fn string_literal() {
+        lex_and_eq(
+            r#""hello world""#,
+            [TokenKind::StringLiteral(interned!("hello world"))].into_iter(),
+        );
+        lex_and_eq(
+            r#""a\n\t\\\"\'b""#,
+            [TokenKind::StringLiteral(interned!("a\n\t\\\"'b"))].into_iter(),
+        );
+    }
+
+    #[test]
+    fn string_literal_unicode() {
+        lex_and_eq(
+            r#""é \u{1F600}""#,
+            [TokenKind::StringLiteral(interned!("é \u{1F600}"))].into_iter(),
+        );
+    }
+
+    #[test]
+    fn char_literal() {
+        lex_and_eq("'a'", [TokenKind::CharLiteral('a')].into_iter());
+        lex_and_eq(r"'\''", [TokenKind::CharLiteral('\'')].into_iter());
+        lex_and_eq(r"'\u{e9}'", [TokenKind::CharLiteral('é')].into_iter());
+    }
+
+    #[test]
+    fn unterminated_literals() {
+        let tokens = crate::lexer::lex(r#"1 "abc\"d"#);
+        assert_eq!(tokens.errors().len(), 1);
+        assert_eq!(tokens.errors()[0].span(), &(2..3));
+
+        let tokens = crate::lexer::lex("'a");
+        assert_eq!(tokens.errors().len(), 1);
+        assert_eq!(tokens.errors()[0].span(), &(0..1));
+    }
+
+    #[test]
+    fn escaped_closing_quote() {
+        for (input, msg) in [
+            (r#""abc\""#, "unterminated string literal"),
+            (r"'\'", "unterminated character literal"),
+        ] {
+            let tokens = crate::lexer::lex(input);
+            let crate::ErrorKind::General(general) = tokens.errors()[0].kind() else {
+                panic!("expected a general error for {input:?}");
+            };
+
+            assert_eq!(tokens.errors().len(), 1, "errors for {input:?}");
+            assert_eq!(tokens.errors()[0].span(), &(0..1));
+            assert_eq!(general, msg);
+        }
+
+        // An escaped backslash leaves the closing quote unescaped.
+        lex_and_eq(
+            r#""a\\""#,
+            [TokenKind::StringLiteral(interned!("a\\"))].into_iter(),
+        );
+    }
+
+    #[test]
+    fn invalid_escapes() {
+        let tokens = crate::lexer::lex(r#""ab\q""#);
+        assert_eq!(tokens.errors().len(), 1);
+        assert_eq!(tokens.errors()[0].span(), &(4..5));
+
+        let tokens = crate::lexer::lex(r#""\u{12G4}""#);
+        assert_eq!(tokens.errors().len(), 1);
+        assert_eq!(tokens.errors()[0].span(), &(6..7));
+
+        let tokens = crate::lexer::lex("'ab'");
+        assert_eq!(tokens.errors().len(), 1);
+    }
+
+    #[test]
+    fn overlapping_operators() {
+        use TokenKind::*;
+
+        let cases: [(&str, &[TokenKind]); 14] = [
+            ("<", &[Less]),
+            ("<=", &[LessEq]),
+            ("<<", &[Shl]),
+            ("<<<", &[Shl, Less]),
+            ("<<=", &[Shl, Eq]),
+            (">", &[Greater]),
+            (">=", &[GreaterEq]),
+            (">>", &[Shr]),
+            (">>=", &[Shr, Eq]),
+            ("=", &[Eq]),
+            ("==", &[Eq, Eq]),
+            ("=>", &[Flow]),
+            ("!=", &[NotEq]),
+            ("!", &[Error]),
+        ];
+
+        for (input, expected) in cases {
+            let lexed = crate::lexer::lex(input)
+                .map(|(kind, _)| kind)
+                .collect::<Vec<_>>();
+            assert_eq!(lexed, expected, "lexing {input:?}");
+        }
+    }
+
+    #[test]
+    fn underscore_symbols() {
+        lex_and_eq("_a", [TokenKind::Symbol(interned!("_a"))].into_iter());
+        lex_and_eq("_1a", [TokenKind::Symbol(interned!("_1a"))].into_iter());
+    }
 }
@@ -20,11 +20,14 @@
 
 use ariadne::Report;
 use lexer::TokenKind;
+use types::Type;
 
-// pub mod ssa;
 pub mod defs;
 pub mod lexer;
 pub mod parser;
+pub mod query;
+pub mod span;
+pub mod ssa;
 pub mod strings;
 pub mod types;
 
@@ -34,6 +37,7 @@ mod tests;
 pub type Span = logos::Span;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ErrorKind {
     General(String),
 
@@ -53,14 +57,63 @@ pub enum ErrorKind {
         var_name: String,
     },
 
+    TypeMismatch {
+        expected: Type,
+        found: Type,
+    },
+
     NoTle,
 }
 
+/// Options for rendering an [`Error`] as a report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReportConfig {
+    /// Whether to emit ANSI color codes.
+    pub color: bool,
+}
+
+impl ReportConfig {
+    /// Enables color only if stderr is a terminal and `NO_COLOR` is unset or empty.
+    pub fn from_env() -> Self {
+        use std::io::IsTerminal;
+
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+
+        Self {
+            color: !no_color && std::io::stderr().is_terminal(),
+        }
+    }
+}
+
+impl Default for ReportConfig {
+    fn default() -> Self {
+        Self { color: true }
+    }
+}
+
+impl ErrorKind {
+    /// A stable code identifying this kind of diagnostic, shown in its report.
+    ///
+    /// Codes are never reused, so tooling and tests can match on them instead of the message.
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::NoTle => "E0001",
+            Self::UndeclaredVar { .. } => "E0002",
+            Self::Unexpected { .. } => "E0003",
+            Self::UnclosedDelimiter { .. } => "E0004",
+            Self::TypeMismatch { .. } => "E0005",
+            Self::General(_) => "E0007",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Error {
     span: Span,
     kind: Box<ErrorKind>,
     label: Option<&'static str>,
+    label_span: Option<Span>,
 }
 
 impl Error {
@@ -69,6 +122,7 @@ impl Error {
             span,
             kind: Box::new(ErrorKind::General(msg.to_owned())),
             label,
+            label_span: None,
         }
     }
 
@@ -82,6 +136,7 @@ impl Error {
             span,
             kind: Box::new(ErrorKind::Unexpected { expected, found }),
             label,
+            label_span: None,
         }
     }
 
@@ -92,6 +147,21 @@ impl Error {
                 var_name: var_name.to_owned(),
             }),
             label,
+            label_span: None,
+        }
+    }
+
+    pub fn type_mismatch(
+        span: Span,
+        expected: Type,
+        found: Type,
+        label: Option<&'static str>,
+    ) -> Self {
+        Self {
+            span,
+            kind: Box::new(ErrorKind::TypeMismatch { expected, found }),
+            label,
+            label_span: None,
         }
     }
 
@@ -100,6 +170,7 @@ impl Error {
             span: 0..0,
             kind: Box::new(ErrorKind::NoTle),
             label: None,
+            label_span: None,
         }
     }
 
@@ -115,6 +186,17 @@ impl Error {
         self.label
     }
 
+    /// The input consumed by the labelled parser before it failed, if it was recorded.
+    pub fn label_span(&self) -> Option<&Span> {
+        self.label_span.as_ref()
+    }
+
+    #[must_use]
+    pub(crate) fn with_label_span(mut self, span: Span) -> Self {
+        self.label_span = Some(span);
+        self
+    }
+
     fn label_msg(&self, msg: &str) -> String {
         match self.label() {
             Some(label) => format!("[{label}] {msg}"),
@@ -122,13 +204,42 @@ impl Error {
         }
     }
 
-    pub fn generate_report(&self) -> Report {
+    /// A report label pointing at where the labelled parser started, if that was recorded.
+    fn label_origin(&self, source: &str) -> Option<ariadne::Label> {
+        let (label, span) = (self.label()?, self.label_span()?);
+
+        Some(
+            ariadne::Label::new(span::snap_to_char_boundary(
+                source,
+                &(span.start..span.start + 1),
+            ))
+            .with_message(format!("while parsing {label} (starting here)")),
+        )
+    }
+
+    /// Builds a report for rendering against `source`, the input this error was produced from.
+    pub fn generate_report(&self, source: &str) -> Report<'_> {
+        self.generate_report_with(source, ReportConfig::default())
+    }
+
+    #[allow(clippy::too_many_lines)]
+    pub fn generate_report_with(&self, source: &str, config: ReportConfig) -> Report<'_> {
         use ariadne::*;
 
+        // Spans are byte offsets, so labels must not split a multi-byte character.
+        let label = |span: &crate::Span| Label::new(span::snap_to_char_boundary(source, span));
+        let report = Report::build(ReportKind::Error, (), 8)
+            .with_code(self.kind().code())
+            .with_config(
+                Config::default()
+                    .with_color(config.color)
+                    .with_index_type(IndexType::Byte),
+            );
+
         match self.kind() {
-            ErrorKind::General(msg) => Report::build(ReportKind::Error, (), 8)
+            ErrorKind::General(msg) => report
                 .with_message(msg)
-                .with_label(Label::new(self.span().clone()))
+                .with_label(label(self.span()))
                 .finish(),
 
             ErrorKind::Unexpected { expected, found } => {
@@ -142,13 +253,15 @@ impl Error {
                     msg.push_str(format!(", found '{found}'").as_str());
                 }
 
-                let mut report = Report::build(ReportKind::Error, (), 8)
-                    .with_message(msg)
-                    .with_label(
-                        Label::new(self.span().clone())
-                            .with_message("compiler did not expect this")
-                            .with_color(Color::Default),
-                    );
+                let mut report = report.with_message(msg).with_label(
+                    label(self.span())
+                        .with_message("compiler did not expect this")
+                        .with_color(Color::Primary),
+                );
+
+                if let Some(origin) = self.label_origin(source) {
+                    report = report.with_label(origin);
+                }
 
                 match expected.len() {
                     1 => report = report.with_note(format!("expected '{}'", expected[0])),
@@ -166,24 +279,49 @@ impl Error {
                     _ => {}
                 }
 
+                // Two values back-to-back where a list could have continued, e.g. `(a b)`.
+                let expected_separator = expected.iter().any(|t| {
+                    matches!(
+                        t,
+                        TokenKind::Separator
+                            | TokenKind::Terminator
+                            | TokenKind::GroupClose
+                            | TokenKind::ArrayClose
+                            | TokenKind::BlockClose
+                    )
+                });
+                if expected_separator && found.as_ref().is_some_and(TokenKind::starts_expr) {
+                    report = report.with_help("did you forget a separator?");
+                }
+
                 report.finish()
             }
 
             ErrorKind::UnclosedDelimiter {
                 delimiter,
-                delimiter_span: _,
+                delimiter_span,
                 expected,
                 found: _,
-            } => Report::build(ReportKind::Error, (), 8)
+            } => report
                 .with_message("unclosed delimiter")
                 .with_label(
-                    Label::new(self.span().clone())
-                        .with_message("expected delimiter for this block")
-                        .with_color(Color::Default),
+                    label(self.span())
+                        .with_message(format!("expected '{expected}' here"))
+                        .with_color(Color::Primary),
+                )
+                // Both ends of the block share one report, with the opening delimiter as a secondary label.
+                .with_label(
+                    label(delimiter_span)
+                        .with_message(format!("unclosed '{delimiter}' opened here"))
+                        .with_color(Color::Cyan),
                 )
                 .with_help(format!(
                     "try inserting {} at the end of the {}",
-                    expected.fg(Color::Green),
+                    if config.color {
+                        expected.fg(Color::Green).to_string()
+                    } else {
+                        expected.to_string()
+                    },
                     match delimiter {
                         TokenKind::ArrayOpen => "array declaration",
                         TokenKind::GroupOpen => "grouping",
@@ -192,12 +330,28 @@ impl Error {
                 ))
                 .finish(),
 
-            ErrorKind::UndeclaredVar { var_name } => Report::build(ReportKind::Error, (), 8)
+            ErrorKind::UndeclaredVar { var_name } => report
                 .with_message(format!("use of undeclared variable `{var_name}`"))
-                .with_label(Label::new(self.span().clone()))
+                .with_label(label(self.span()))
                 .finish(),
 
-            ErrorKind::NoTle => Report::build(ReportKind::Error, (), 8)
+            ErrorKind::TypeMismatch { expected, found } => {
+                let mut report = report
+                    .with_message(self.label_msg("mismatched types"))
+                    .with_label(
+                        label(self.span())
+                            .with_message(format!("expected `{expected}`, found `{found}`"))
+                            .with_color(Color::Primary),
+                    );
+
+                if let Some(note) = expected.diff_note(found) {
+                    report = report.with_note(note);
+                }
+
+                report.finish()
+            }
+
+            ErrorKind::NoTle => report
                 .with_message("script has no top-level expression")
                 .finish(),
         }
@@ -220,6 +374,7 @@ impl chumsky::Error<TokenKind> for Error {
                 found,
             }),
             label: None,
+            label_span: None,
         }
     }
 
@@ -239,6 +394,7 @@ impl chumsky::Error<TokenKind> for Error {
                 found,
             }),
             label: None,
+            label_span: None,
         }
     }
 
@@ -247,16 +403,50 @@ impl chumsky::Error<TokenKind> for Error {
             span: self.span,
             kind: self.kind,
             label: Some(label),
+            // The span belongs to whichever label it was recorded for.
+            label_span: None,
         }
     }
 
-    fn merge(self, _other: Self) -> Self {
-        // FIXME: Actually merge the errors?
-        self
+    fn merge(mut self, other: Self) -> Self {
+        match (&mut *self.kind, *other.kind) {
+            (
+                ErrorKind::Unexpected { expected, found },
+                ErrorKind::Unexpected {
+                    expected: other_expected,
+                    found: other_found,
+                },
+            ) => {
+                for token in other_expected {
+                    if !expected.contains(&token) {
+                        expected.push(token);
+                    }
+                }
+
+                if !span::overlaps(&self.span, &other.span) {
+                    // Underline the whole ambiguous region between the two positions.
+                    self.span = span::merge(&self.span, &other.span);
+                } else if other.span.len() < self.span.len() {
+                    // The narrower error points more precisely at the offending token.
+                    *found = other_found;
+                    self.span = other.span;
+                }
+
+                self
+            }
+
+            (_, other_kind) if other.span.len() > self.span.len() => Self {
+                kind: Box::new(other_kind),
+                ..other
+            },
+
+            _ => self,
+        }
     }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Operator {
     Exp,
     Add,
@@ -290,6 +480,32 @@ pub enum Operator {
 }
 
 impl Operator {
+    pub const ALL: [Self; 23] = [
+        Self::Exp,
+        Self::Add,
+        Self::Sub,
+        Self::Mul,
+        Self::Div,
+        Self::Rem,
+        Self::Shr,
+        Self::Shl,
+        Self::BitXor,
+        Self::BitAnd,
+        Self::BitOr,
+        Self::Eq,
+        Self::NotEq,
+        Self::Greater,
+        Self::GreaterEq,
+        Self::Less,
+        Self::LessEq,
+        Self::Or,
+        Self::Xor,
+        Self::And,
+        Self::Clow,
+        Self::Cerm,
+        Self::Assign,
+    ];
+
     #[inline]
     pub const fn is_arithmetic(&self) -> bool {
         matches!(
@@ -331,6 +547,80 @@ impl Operator {
                 | Self::And
         )
     }
+
+    /// Binding power of the operator; higher values bind more tightly. The parser builds one level
+    /// per distinct value, so operators sharing a precedence must share an associativity.
+    pub const fn precedence(&self) -> u8 {
+        match self {
+            Self::Exp => 13,
+            Self::Mul | Self::Div | Self::Rem => 12,
+            Self::Add | Self::Sub => 11,
+            Self::Shr | Self::Shl => 10,
+            Self::BitAnd => 9,
+            Self::BitXor => 8,
+            Self::BitOr => 7,
+            Self::Eq
+            | Self::NotEq
+            | Self::Greater
+            | Self::GreaterEq
+            | Self::Less
+            | Self::LessEq => 6,
+            Self::And => 5,
+            Self::Xor => 4,
+            Self::Or => 3,
+            Self::Clow | Self::Cerm => 2,
+            Self::Assign => 1,
+        }
+    }
+
+    pub const fn associativity(&self) -> Associativity {
+        match self {
+            Self::Exp | Self::Assign => Associativity::Right,
+            _ => Associativity::Left,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Associativity {
+    Left,
+    Right,
+}
+
+impl TryFrom<&TokenKind> for Operator {
+    /// The token, returned as-is when it isn't an operator.
+    type Error = TokenKind;
+
+    fn try_from(token: &TokenKind) -> Result<Self, Self::Error> {
+        let op = match token {
+            TokenKind::Exp => Self::Exp,
+            TokenKind::Add => Self::Add,
+            TokenKind::Sub => Self::Sub,
+            TokenKind::Mul => Self::Mul,
+            TokenKind::Div => Self::Div,
+            TokenKind::Rem => Self::Rem,
+            TokenKind::Shr => Self::Shr,
+            TokenKind::Shl => Self::Shl,
+            TokenKind::BitXor => Self::BitXor,
+            TokenKind::BitAnd => Self::BitAnd,
+            TokenKind::BitOr => Self::BitOr,
+            TokenKind::Eq => Self::Eq,
+            TokenKind::NotEq => Self::NotEq,
+            TokenKind::Greater => Self::Greater,
+            TokenKind::GreaterEq => Self::GreaterEq,
+            TokenKind::Less => Self::Less,
+            TokenKind::LessEq => Self::LessEq,
+            TokenKind::Or => Self::Or,
+            TokenKind::Xor => Self::Xor,
+            TokenKind::And => Self::And,
+            TokenKind::Clow => Self::Clow,
+            TokenKind::Cerm => Self::Cerm,
+            TokenKind::Assign => Self::Assign,
+            token => return Err(*token),
+        };
+
+        Ok(op)
+    }
 }
 
 #[macro_export]
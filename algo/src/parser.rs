@@ -1,19 +1,27 @@
-use crate::{lexer::TokenKind, strings::Symbol, types::Type, Error, Operator, Span};
+use crate::{
+    lexer::{Token, TokenKind},
+    strings::Symbol,
+    types::Type,
+    Associativity, Error, Operator, Span,
+};
 use chumsky::{
-    primitive::{choice, end, just},
-    recovery::nested_delimiters,
+    primitive::{choice, end, filter_map, just},
+    recovery::{nested_delimiters, skip_until},
     recursive::recursive,
-    select, BoxedParser, Parser,
+    select, BoxedParser, Error as _, Parser,
 };
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ExpressionKind {
     Error,
 
     Unit,
     Int(isize),
     UInt(usize),
+    Float(f64),
     Bool(bool),
+    Str(Symbol),
+    Char(char),
     Array(Vec<Expression>),
     Tuple(Vec<Expression>),
 
@@ -28,11 +36,36 @@ pub enum ExpressionKind {
 
     Compound(Vec<Expression>),
 
+    /// Positional tuple access, i.e. `tuple.0`.
+    TupleIndex {
+        tuple: Box<Expression>,
+        index: usize,
+    },
+
+    /// Named tuple access, i.e. `tuple.name`.
+    TupleField {
+        tuple: Box<Expression>,
+        field: Symbol,
+    },
+
+    /// Array element access, i.e. `array[index]`.
+    ArrayIndex {
+        array: Box<Expression>,
+        index: Box<Expression>,
+    },
+
     ControlFlow {
         from: Box<Expression>,
         into: Option<Box<Expression>>,
     },
 
+    /// `if cond { then } else { otherwise }`, where the `else` block is optional.
+    If {
+        cond: Box<Expression>,
+        then: Box<Expression>,
+        otherwise: Option<Box<Expression>>,
+    },
+
     VarDef {
         name: Symbol,
         ty: Type,
@@ -45,10 +78,11 @@ pub enum ExpressionKind {
     },
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Expression {
     kind: ExpressionKind,
     span: Span,
+    token: Option<TokenKind>,
 }
 
 impl Expression {
@@ -61,6 +95,513 @@ impl Expression {
     pub const fn span(&self) -> &Span {
         &self.span
     }
+
+    /// The token a literal expression was parsed from, or `None` for non-literals.
+    #[inline]
+    pub const fn literal_token(&self) -> Option<&TokenKind> {
+        self.token.as_ref()
+    }
+}
+
+/// A parsed script, spanning from its first token to its last.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    items: Vec<Expression>,
+    span: Span,
+}
+
+impl Program {
+    #[inline]
+    pub fn items(&self) -> &[Expression] {
+        &self.items
+    }
+
+    #[inline]
+    pub fn into_items(self) -> Vec<Expression> {
+        self.items
+    }
+
+    #[inline]
+    pub const fn span(&self) -> &Span {
+        &self.span
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AstPathSegment {
+    Element(usize),
+    Lhs,
+    Rhs,
+    From,
+    Into,
+    Expr,
+    Cond,
+    Then,
+    Else,
+    Index,
+}
+
+/// Describes the first point at which two expression trees structurally diverge.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AstDiff {
+    path: Vec<AstPathSegment>,
+    lhs_span: Span,
+    rhs_span: Span,
+}
+
+impl AstDiff {
+    #[inline]
+    pub fn path(&self) -> &[AstPathSegment] {
+        &self.path
+    }
+
+    #[inline]
+    pub const fn lhs_span(&self) -> &Span {
+        &self.lhs_span
+    }
+
+    #[inline]
+    pub const fn rhs_span(&self) -> &Span {
+        &self.rhs_span
+    }
+}
+
+impl core::fmt::Display for AstDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("<root>")?;
+
+        for segment in &self.path {
+            match segment {
+                AstPathSegment::Element(index) => write!(f, "[{index}]")?,
+                AstPathSegment::Lhs => f.write_str(".lhs")?,
+                AstPathSegment::Rhs => f.write_str(".rhs")?,
+                AstPathSegment::From => f.write_str(".from")?,
+                AstPathSegment::Into => f.write_str(".into")?,
+                AstPathSegment::Expr => f.write_str(".expr")?,
+                AstPathSegment::Cond => f.write_str(".cond")?,
+                AstPathSegment::Then => f.write_str(".then")?,
+                AstPathSegment::Else => f.write_str(".else")?,
+                AstPathSegment::Index => f.write_str(".index")?,
+            }
+        }
+
+        write!(f, " ({:?} != {:?})", self.lhs_span, self.rhs_span)
+    }
+}
+
+/// Structurally compares two expression trees, ignoring spans.
+///
+/// Returns `None` if the trees are equal, otherwise the path to the first differing node.
+pub fn diff_ast(a: &Expression, b: &Expression) -> Option<AstDiff> {
+    let mut path = Vec::new();
+    diff_expr(a, b, &mut path).map(|(lhs_span, rhs_span)| AstDiff {
+        path,
+        lhs_span,
+        rhs_span,
+    })
+}
+
+#[allow(clippy::too_many_lines)]
+fn diff_expr(
+    a: &Expression,
+    b: &Expression,
+    path: &mut Vec<AstPathSegment>,
+) -> Option<(Span, Span)> {
+    use ExpressionKind::*;
+
+    let diverged = || Some((a.span.clone(), b.span.clone()));
+    match (a.kind(), b.kind()) {
+        (Array(a_exprs), Array(b_exprs))
+        | (Tuple(a_exprs), Tuple(b_exprs))
+        | (Compound(a_exprs), Compound(b_exprs)) => a_exprs
+            .iter()
+            .zip(b_exprs.iter())
+            .enumerate()
+            .find_map(|(index, (a, b))| diff_child(a, b, AstPathSegment::Element(index), path))
+            .or_else(|| {
+                if a_exprs.len() == b_exprs.len() {
+                    None
+                } else {
+                    diverged()
+                }
+            }),
+
+        (
+            Binary {
+                lhs: a_lhs,
+                op: a_op,
+                rhs: a_rhs,
+            },
+            Binary {
+                lhs: b_lhs,
+                op: b_op,
+                rhs: b_rhs,
+            },
+        ) if a_op == b_op => diff_child(a_lhs, b_lhs, AstPathSegment::Lhs, path)
+            .or_else(|| diff_child(a_rhs, b_rhs, AstPathSegment::Rhs, path)),
+
+        (
+            ControlFlow {
+                from: a_from,
+                into: a_into,
+            },
+            ControlFlow {
+                from: b_from,
+                into: b_into,
+            },
+        ) => diff_child(a_from, b_from, AstPathSegment::From, path).or_else(|| {
+            match (a_into, b_into) {
+                (Some(a_into), Some(b_into)) => {
+                    diff_child(a_into, b_into, AstPathSegment::Into, path)
+                }
+                (None, None) => None,
+                _ => diverged(),
+            }
+        }),
+
+        (
+            If {
+                cond: a_cond,
+                then: a_then,
+                otherwise: a_otherwise,
+            },
+            If {
+                cond: b_cond,
+                then: b_then,
+                otherwise: b_otherwise,
+            },
+        ) => diff_child(a_cond, b_cond, AstPathSegment::Cond, path)
+            .or_else(|| diff_child(a_then, b_then, AstPathSegment::Then, path))
+            .or_else(|| match (a_otherwise, b_otherwise) {
+                (Some(a_otherwise), Some(b_otherwise)) => {
+                    diff_child(a_otherwise, b_otherwise, AstPathSegment::Else, path)
+                }
+                (None, None) => None,
+                _ => diverged(),
+            }),
+
+        (
+            TupleIndex {
+                tuple: a_tuple,
+                index: a_index,
+            },
+            TupleIndex {
+                tuple: b_tuple,
+                index: b_index,
+            },
+        ) if a_index == b_index => diff_child(a_tuple, b_tuple, AstPathSegment::Expr, path),
+
+        (
+            TupleField {
+                tuple: a_tuple,
+                field: a_field,
+            },
+            TupleField {
+                tuple: b_tuple,
+                field: b_field,
+            },
+        ) if a_field == b_field => diff_child(a_tuple, b_tuple, AstPathSegment::Expr, path),
+
+        (
+            ArrayIndex {
+                array: a_array,
+                index: a_index,
+            },
+            ArrayIndex {
+                array: b_array,
+                index: b_index,
+            },
+        ) => diff_child(a_array, b_array, AstPathSegment::Expr, path)
+            .or_else(|| diff_child(a_index, b_index, AstPathSegment::Index, path)),
+
+        (
+            VarDef {
+                name: a_name,
+                ty: a_ty,
+                expr: a_expr,
+            },
+            VarDef {
+                name: b_name,
+                ty: b_ty,
+                expr: b_expr,
+            },
+        ) if a_name == b_name && a_ty == b_ty => {
+            diff_child(a_expr, b_expr, AstPathSegment::Expr, path)
+        }
+
+        // Remaining matching kinds are leaves, so comparing them directly never compares spans.
+        (
+            a_kind @ (Error
+            | Unit
+            | Int(_)
+            | UInt(_)
+            | Float(_)
+            | Bool(_)
+            | Str(_)
+            | Char(_)
+            | TypeId(_)
+            | Identifier(_)
+            | TypeDef { .. }),
+            b_kind,
+        ) if a_kind == b_kind => None,
+
+        _ => diverged(),
+    }
+}
+
+fn diff_child(
+    a: &Expression,
+    b: &Expression,
+    segment: AstPathSegment,
+    path: &mut Vec<AstPathSegment>,
+) -> Option<(Span, Span)> {
+    path.push(segment);
+    let diff = diff_expr(a, b, path);
+    if diff.is_none() {
+        path.pop();
+    }
+
+    diff
+}
+
+impl Expression {
+    /// Folds constant arithmetic and logic on literal operands, e.g. `2 * (3 + 4)` into `14`.
+    ///
+    /// # Errors
+    ///
+    /// Operations that would fail at runtime, such as dividing by zero or overflowing.
+    pub fn try_reduce(self) -> Result<Self, Error> {
+        use ExpressionKind::*;
+
+        fn reduce_box(expr: Expression) -> Result<Box<Expression>, crate::Error> {
+            expr.try_reduce().map(Box::new)
+        }
+
+        fn reduce_all(exprs: Vec<Expression>) -> Result<Vec<Expression>, crate::Error> {
+            exprs.into_iter().map(Expression::try_reduce).collect()
+        }
+
+        let kind = match self.kind {
+            Binary { lhs, op, rhs } => {
+                let (lhs, rhs) = (lhs.try_reduce()?, rhs.try_reduce()?);
+
+                match fold_binary(&lhs.kind, op, &rhs.kind, &self.span)? {
+                    Some(kind) => kind,
+                    None => Binary {
+                        lhs: Box::new(lhs),
+                        op,
+                        rhs: Box::new(rhs),
+                    },
+                }
+            }
+
+            Array(exprs) => Array(reduce_all(exprs)?),
+            Tuple(exprs) => Tuple(reduce_all(exprs)?),
+            Compound(exprs) => Compound(reduce_all(exprs)?),
+            TupleIndex { tuple, index } => TupleIndex {
+                tuple: reduce_box(*tuple)?,
+                index,
+            },
+            TupleField { tuple, field } => TupleField {
+                tuple: reduce_box(*tuple)?,
+                field,
+            },
+            ArrayIndex { array, index } => ArrayIndex {
+                array: reduce_box(*array)?,
+                index: reduce_box(*index)?,
+            },
+            ControlFlow { from, into } => ControlFlow {
+                from: reduce_box(*from)?,
+                into: into.map(|expr| reduce_box(*expr)).transpose()?,
+            },
+            If {
+                cond,
+                then,
+                otherwise,
+            } => If {
+                cond: reduce_box(*cond)?,
+                then: reduce_box(*then)?,
+                otherwise: otherwise.map(|expr| reduce_box(*expr)).transpose()?,
+            },
+            VarDef { name, ty, expr } => VarDef {
+                name,
+                ty,
+                expr: reduce_box(*expr)?,
+            },
+
+            kind @ (Error
+            | Unit
+            | Int(_)
+            | UInt(_)
+            | Float(_)
+            | Bool(_)
+            | Str(_)
+            | Char(_)
+            | TypeId(_)
+            | Identifier(_)
+            | TypeDef { .. }) => kind,
+        };
+
+        Ok(Self {
+            kind,
+            span: self.span,
+            token: self.token,
+        })
+    }
+}
+
+/// Evaluates `lhs op rhs` if both sides are literals of a foldable type, or returns `None` to leave
+/// the expression as-is.
+fn fold_binary(
+    lhs: &ExpressionKind,
+    op: Operator,
+    rhs: &ExpressionKind,
+    span: &Span,
+) -> Result<Option<ExpressionKind>, Error> {
+    match (lhs, rhs) {
+        (ExpressionKind::Int(lhs), ExpressionKind::Int(rhs)) => fold_int(*lhs, op, *rhs)
+            .map_err(|msg| Error::general(span.clone(), &msg, Some("try_reduce"))),
+        (ExpressionKind::UInt(lhs), ExpressionKind::UInt(rhs)) => fold_uint(*lhs, op, *rhs)
+            .map_err(|msg| Error::general(span.clone(), &msg, Some("try_reduce"))),
+        // A negative literal can never be a valid `UInt` exponent, whatever its eventual type.
+        (ExpressionKind::UInt(_), ExpressionKind::Int(rhs)) if op == Operator::Exp && *rhs < 0 => {
+            Err(Error::general(
+                span.clone(),
+                &format!("exponent {rhs} is out of range for `UInt`"),
+                Some("try_reduce"),
+            ))
+        }
+        (ExpressionKind::Bool(lhs), ExpressionKind::Bool(rhs)) => Ok(fold_bool(*lhs, op, *rhs)),
+        _ => Ok(None),
+    }
+}
+
+/// Defines a folding function for one integer type, which only differ in their literal kind.
+macro_rules! fold_integer {
+    ($name:ident, $int:ty, $kind:ident, $type_name:literal) => {
+        fn $name(lhs: $int, op: Operator, rhs: $int) -> Result<Option<ExpressionKind>, String> {
+            let overflow = || "arithmetic overflow in constant expression".to_owned();
+            let shift = |shift: fn($int, u32) -> Option<$int>| {
+                u32::try_from(rhs)
+                    .ok()
+                    .and_then(|amount| shift(lhs, amount))
+                    .ok_or_else(|| {
+                        format!("shift amount {rhs} is out of range for `{}`", $type_name)
+                    })
+            };
+
+            let int = match op {
+                Operator::Add => lhs.checked_add(rhs).ok_or_else(overflow)?,
+                Operator::Sub => lhs.checked_sub(rhs).ok_or_else(overflow)?,
+                Operator::Mul => lhs.checked_mul(rhs).ok_or_else(overflow)?,
+                Operator::Div | Operator::Rem if rhs == 0 => {
+                    return Err("division by zero".to_owned())
+                }
+                Operator::Div => lhs.checked_div(rhs).ok_or_else(overflow)?,
+                Operator::Rem => lhs.checked_rem(rhs).ok_or_else(overflow)?,
+                Operator::Exp => {
+                    let exponent = u32::try_from(rhs).map_err(|_| {
+                        format!("exponent {rhs} is out of range for `{}`", $type_name)
+                    })?;
+                    lhs.checked_pow(exponent).ok_or_else(overflow)?
+                }
+                Operator::Shl => shift(<$int>::checked_shl)?,
+                Operator::Shr => shift(<$int>::checked_shr)?,
+                Operator::BitAnd => lhs & rhs,
+                Operator::BitOr => lhs | rhs,
+                Operator::BitXor => lhs ^ rhs,
+
+                Operator::Eq => return Ok(Some(ExpressionKind::Bool(lhs == rhs))),
+                Operator::NotEq => return Ok(Some(ExpressionKind::Bool(lhs != rhs))),
+                Operator::Greater => return Ok(Some(ExpressionKind::Bool(lhs > rhs))),
+                Operator::GreaterEq => return Ok(Some(ExpressionKind::Bool(lhs >= rhs))),
+                Operator::Less => return Ok(Some(ExpressionKind::Bool(lhs < rhs))),
+                Operator::LessEq => return Ok(Some(ExpressionKind::Bool(lhs <= rhs))),
+
+                Operator::Or
+                | Operator::Xor
+                | Operator::And
+                | Operator::Clow
+                | Operator::Cerm
+                | Operator::Assign => return Ok(None),
+            };
+
+            Ok(Some(ExpressionKind::$kind(int)))
+        }
+    };
+}
+
+fold_integer!(fold_int, isize, Int, "Int");
+fold_integer!(fold_uint, usize, UInt, "UInt");
+
+fn fold_bool(lhs: bool, op: Operator, rhs: bool) -> Option<ExpressionKind> {
+    let bool = match op {
+        Operator::And => lhs && rhs,
+        Operator::Or => lhs || rhs,
+        Operator::Xor | Operator::NotEq => lhs != rhs,
+        Operator::Eq => lhs == rhs,
+        _ => return None,
+    };
+
+    Some(ExpressionKind::Bool(bool))
+}
+
+/// The full source range covered by `expr`, merging its own span with those of all its children.
+pub fn node_span(expr: &Expression) -> Span {
+    use ExpressionKind::*;
+
+    let merge_all = |exprs: &[Expression]| {
+        exprs.iter().fold(expr.span.clone(), |span, child| {
+            crate::span::merge(&span, &node_span(child))
+        })
+    };
+
+    match &expr.kind {
+        Array(elements) | Tuple(elements) | Compound(elements) => merge_all(elements),
+        Binary { lhs, rhs, .. } => crate::span::merge(
+            &expr.span,
+            &crate::span::merge(&node_span(lhs), &node_span(rhs)),
+        ),
+        ControlFlow { from, into } => {
+            let span = crate::span::merge(&expr.span, &node_span(from));
+            match into {
+                Some(into) => crate::span::merge(&span, &node_span(into)),
+                None => span,
+            }
+        }
+        If {
+            cond,
+            then,
+            otherwise,
+        } => {
+            let span = crate::span::merge(
+                &expr.span,
+                &crate::span::merge(&node_span(cond), &node_span(then)),
+            );
+            match otherwise {
+                Some(otherwise) => crate::span::merge(&span, &node_span(otherwise)),
+                None => span,
+            }
+        }
+        ArrayIndex { array, index } => crate::span::merge(
+            &expr.span,
+            &crate::span::merge(&node_span(array), &node_span(index)),
+        ),
+        VarDef { expr: body, .. }
+        | TupleIndex { tuple: body, .. }
+        | TupleField { tuple: body, .. } => crate::span::merge(&expr.span, &node_span(body)),
+        Error
+        | Unit
+        | Int(_)
+        | UInt(_)
+        | Float(_)
+        | Bool(_)
+        | Str(_)
+        | Char(_)
+        | TypeId(_)
+        | Identifier(_)
+        | TypeDef { .. } => expr.span.clone(),
+    }
 }
 
 macro_rules! expr {
@@ -68,34 +609,124 @@ macro_rules! expr {
         Expression {
             kind: $kind,
             span: $span,
+            token: None,
         }
     };
 }
 
-pub fn parse(tokens: crate::lexer::Tokens) -> Result<Vec<Expression>, Vec<Error>> {
-    parse_aggregate().parse(tokens)
+/// Settings that change what [`parse_with`] accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseConfig {
+    /// Maximum number of elements in a tuple or array, guarding against pathological input.
+    pub max_arity: usize,
+}
+
+impl Default for ParseConfig {
+    fn default() -> Self {
+        Self { max_arity: 255 }
+    }
+}
+
+/// Parses `tokens` into a [`Program`] with the default [`ParseConfig`].
+///
+/// # Errors
+///
+/// Returns the lexer's errors if there were any, otherwise every error found while parsing.
+pub fn parse(tokens: crate::lexer::Tokens) -> Result<Program, Vec<Error>> {
+    parse_with(tokens, ParseConfig::default())
+}
+
+/// Parses `tokens` into a [`Program`], accepting only what `config` allows.
+///
+/// # Errors
+///
+/// Returns the lexer's errors if there were any, otherwise every error found while parsing.
+pub fn parse_with(
+    tokens: crate::lexer::Tokens,
+    config: ParseConfig,
+) -> Result<Program, Vec<Error>> {
+    // Lexing errors already explain their `Error` tokens, so don't pile parse errors on top.
+    if !tokens.errors().is_empty() {
+        return Err(tokens.errors().to_vec());
+    }
+
+    let span = tokens.span();
+    parse_aggregate(config)
+        .parse(tokens)
+        .map(|items| Program { items, span })
+}
+
+/// Lexes and parses `input`, also returning the token stream so tooling doesn't need to lex twice.
+///
+/// The tokens are returned even when parsing fails.
+pub fn parse_with_tokens(input: &str) -> (Vec<Token>, Result<Program, Vec<Error>>) {
+    let tokens = crate::lexer::lex(input);
+    let token_list = tokens.clone().collect();
+
+    (token_list, parse(tokens))
 }
 
 type AlgoParser<'a, T> = BoxedParser<'a, TokenKind, T, Error>;
 
-fn parse_aggregate<'a>() -> AlgoParser<'a, Vec<Expression>> {
-    choice((parse_vardef(), parse_control_flow()))
+/// Like [`Parser::labelled`], but also records where the labelled parser started so reports can
+/// point back to it.
+fn labelled_from<'a, T: 'a>(
+    parser: impl 'a + Parser<TokenKind, T, Error = Error>,
+    label: &'static str,
+) -> AlgoParser<'a, T> {
+    parser
+        .labelled(label)
+        .map_err_with_span(Error::with_label_span)
+        .boxed()
+}
+
+fn validate_arity<T>(
+    elements: Vec<T>,
+    span: Span,
+    emit: &mut dyn FnMut(Error),
+    max_arity: usize,
+) -> Vec<T> {
+    if elements.len() > max_arity {
+        emit(Error::general(
+            span,
+            &format!("too many elements (maximum is {max_arity})"),
+            Some("validate_arity"),
+        ));
+    }
+
+    elements
+}
+
+fn parse_aggregate<'a>(config: ParseConfig) -> AlgoParser<'a, Vec<Expression>> {
+    // Resynchronize at the next terminator, so each malformed statement reports on its own.
+    let statement = choice((parse_vardef(config), parse_control_flow(config))).recover_with(
+        skip_until([TokenKind::Terminator], |span| {
+            expr!(ExpressionKind::Error, span)
+        })
+        .consume_end(),
+    );
+
+    // A stray terminator is an empty statement, as in a shell.
+    just(TokenKind::Terminator)
+        .to(None)
+        .or(statement.map(Some))
         .repeated()
         .then_ignore(end())
+        .map(|statements| statements.into_iter().flatten().collect())
         .boxed()
 }
 
-fn parse_vardef<'a>() -> AlgoParser<'a, Expression> {
+fn parse_vardef<'a>(config: ParseConfig) -> AlgoParser<'a, Expression> {
     let unit = select! { TokenKind::TypeUnit => Type::Unit };
-    let body = choice((parse_tuple_type(), unit))
+    let body = choice((parse_tuple_type(config), unit))
         .then_ignore(just(TokenKind::Flow))
-        .then(parse_control_flow())
+        .then(parse_control_flow(config))
         .boxed();
 
     let body_terminated = body.clone().then_ignore(just(TokenKind::Terminator));
     let body_delimited = body.delimited_by(just(TokenKind::BlockOpen), just(TokenKind::BlockClose));
 
-    just(TokenKind::VarDef)
+    let vardef = just(TokenKind::VarDef)
         .ignore_then(parse_symbol())
         .then_ignore(just(TokenKind::Assign))
         .then(choice((body_terminated, body_delimited)))
@@ -107,38 +738,42 @@ fn parse_vardef<'a>() -> AlgoParser<'a, Expression> {
             };
 
             expr!(kind, span)
-        })
-        .labelled("parse_vardef")
-        .boxed()
+        });
+
+    labelled_from(vardef, "parse_vardef")
 }
 
-fn parse_typedef<'a>() -> AlgoParser<'a, Expression> {
-    just(TokenKind::TypeDef)
+fn parse_typedef<'a>(config: ParseConfig) -> AlgoParser<'a, Expression> {
+    let typedef = just(TokenKind::TypeDef)
         .ignore_then(parse_symbol())
         .then_ignore(just(TokenKind::Assign))
-        .then(parse_type())
+        .then(parse_type(config))
         .then_ignore(just(TokenKind::Terminator))
-        .map_with_span(|(name, ty), span| expr!(ExpressionKind::TypeDef { name, ty }, span))
-        .labelled("parse_typedef")
-        .boxed()
+        .map_with_span(|(name, ty), span| expr!(ExpressionKind::TypeDef { name, ty }, span));
+
+    labelled_from(typedef, "parse_typedef")
 }
 
-fn parse_type() -> impl Parser<TokenKind, Type, Error = Error> {
+fn parse_type(config: ParseConfig) -> impl Parser<TokenKind, Type, Error = Error> {
     choice((
-        parse_tuple_type(),
+        parse_tuple_type(config),
         parse_array_type(),
         parse_structural_type(),
     ))
 }
 
-fn parse_tuple_type<'a>() -> AlgoParser<'a, Type> {
+fn parse_tuple_type<'a>(config: ParseConfig) -> AlgoParser<'a, Type> {
     recursive(|expr| {
         parse_symbol()
+            .map(Some)
             .then_ignore(just(TokenKind::Assign))
             .then(choice((expr, parse_array_type(), parse_structural_type())))
             .separated_by(just(TokenKind::Separator))
             .at_least(1)
             .delimited_by(just(TokenKind::GroupOpen), just(TokenKind::GroupClose))
+            .validate(move |fields, span, emit| {
+                validate_arity(fields, span, emit, config.max_arity)
+            })
             .map(Type::Tuple)
     })
     .labelled("parse_tuple_type")
@@ -175,15 +810,24 @@ fn parse_array_type<'a>() -> AlgoParser<'a, Type> {
 fn parse_structural_type() -> impl Parser<TokenKind, Type, Error = Error> {
     select! {
         TokenKind::TypeUnit => Type::Unit,
-        TokenKind::TypeInt => Type::Int,
-        TokenKind::TypeUInt => Type::UInt,
+        TokenKind::TypeInt => Type::INT,
+        TokenKind::TypeUInt => Type::UINT,
+        TokenKind::TypeIntN(width) => Type::Int(width),
+        TokenKind::TypeUIntN(width) => Type::UInt(width),
         TokenKind::TypeBool => Type::Bool,
+        TokenKind::TypeFloat => Type::Float,
+        TokenKind::TypeStr => Type::Str,
+        TokenKind::TypeChar => Type::Char,
     }
 }
 
-fn parse_control_flow<'a>() -> AlgoParser<'a, Expression> {
+fn parse_control_flow<'a>(config: ParseConfig) -> AlgoParser<'a, Expression> {
     recursive(|next| {
-        let expr = choice((parse_tuple(), parse_array())).or(parse_expr());
+        let expr = parse_postfix(
+            choice((parse_tuple(config), parse_array(config))),
+            parse_expr(),
+        )
+        .or(parse_expr());
         let compound_expr = expr
             .clone()
             .separated_by(just(TokenKind::Terminator))
@@ -216,14 +860,34 @@ fn parse_control_flow<'a>() -> AlgoParser<'a, Expression> {
 #[allow(clippy::too_many_lines)]
 fn parse_expr<'a>() -> AlgoParser<'a, Expression> {
     recursive(|expr| {
+        let block = expr
+            .clone()
+            .delimited_by(just(TokenKind::BlockOpen), just(TokenKind::BlockClose));
+        let if_else = just(TokenKind::If)
+            .ignore_then(expr.clone())
+            .then(block.clone())
+            .then(just(TokenKind::Else).ignore_then(block).or_not())
+            .map_with_span(|((cond, then), otherwise), span| {
+                let kind = ExpressionKind::If {
+                    cond: Box::new(cond),
+                    then: Box::new(then),
+                    otherwise: otherwise.map(Box::new),
+                };
+
+                expr!(kind, span)
+            })
+            .labelled("parse_if");
+
         let atom = choice((
-            parse_integer().map_with_span(|int, span| expr!(ExpressionKind::Int(int), span)),
-            parse_uinteger().map_with_span(|uint, span| expr!(ExpressionKind::UInt(uint), span)),
-            parse_bool().map_with_span(|bool, span| expr!(ExpressionKind::Bool(bool), span)),
+            parse_literal(),
             parse_symbol()
                 .map_with_span(|symbol, span| expr!(ExpressionKind::Identifier(symbol), span)),
+            parse_type_as_value(),
+            if_else,
         ))
-        .or(expr.delimited_by(just(TokenKind::GroupOpen), just(TokenKind::GroupClose)))
+        .or(expr
+            .clone()
+            .delimited_by(just(TokenKind::GroupOpen), just(TokenKind::GroupClose)))
         .recover_with(nested_delimiters(
             TokenKind::GroupOpen,
             TokenKind::GroupClose,
@@ -253,6 +917,7 @@ fn parse_expr<'a>() -> AlgoParser<'a, Expression> {
         ))
         .labelled("parse_atom")
         .boxed();
+        let atom = parse_postfix(atom, expr.clone());
 
         fn parse_op<'a>(
             op_parser: impl 'a + Parser<TokenKind, Operator, Error = Error> + Clone,
@@ -274,90 +939,231 @@ fn parse_expr<'a>() -> AlgoParser<'a, Expression> {
                 .boxed()
         }
 
-        let assign = parse_op(select! { TokenKind::Assign => Operator::Assign }, atom);
-        let conditional_flow = parse_op(
-            select! {
-                TokenKind::Clow => Operator::Clow,
-                TokenKind::Cerm => Operator::Cerm,
-            },
-            assign,
-        );
-        let condition = parse_op(
-            select! {
-                TokenKind::Eq => Operator::Eq,
-                TokenKind::NotEq => Operator::NotEq,
-                TokenKind::Greater => Operator::Greater,
-                TokenKind::GreaterEq => Operator::GreaterEq,
-                TokenKind::Less => Operator::Less,
-                TokenKind::LessEq => Operator::LessEq,
-                TokenKind::Or => Operator::Or,
-                TokenKind::Xor => Operator::Xor,
-                TokenKind::And => Operator::And,
+        // Right-associative counterpart to `parse_op`, i.e. `a ** b ** c` parses as `a ** (b ** c)`.
+        fn parse_op_right<'a>(
+            op_parser: impl 'a + Parser<TokenKind, Operator, Error = Error> + Clone,
+            base_parser: AlgoParser<'a, Expression>,
+        ) -> AlgoParser<'a, Expression> {
+            base_parser
+                .clone()
+                .then(op_parser)
+                .repeated()
+                .then(base_parser)
+                .validate(|(operands, rhs), _, emit| {
+                    // Every target in an assignment chain is the lhs of one of its operators.
+                    for (lhs, op) in &operands {
+                        if *op == Operator::Assign {
+                            validate_assign_target(lhs, emit);
+                        }
+                    }
+
+                    (operands, rhs)
+                })
+                .foldr(|(lhs, op), rhs| {
+                    let span = lhs.span.start..rhs.span.end;
+                    let kind = ExpressionKind::Binary {
+                        lhs: Box::new(lhs),
+                        op,
+                        rhs: Box::new(rhs),
+                    };
+
+                    expr!(kind, span)
+                })
+                .boxed()
+        }
+
+        // One level per precedence in `Operator::precedence`, from the tightest binding outwards.
+        let mut precedences = Operator::ALL.map(|op| op.precedence());
+        precedences.sort_unstable_by(|a, b| b.cmp(a));
+
+        let mut level = atom;
+        for (index, &precedence) in precedences.iter().enumerate() {
+            if precedences[..index].contains(&precedence) {
+                continue;
+            }
+
+            let op = filter_map(
+                move |span, token: TokenKind| match Operator::try_from(&token) {
+                    Ok(op) if op.precedence() == precedence => Ok(op),
+                    _ => Err(Error::expected_input_found(span, None, Some(token))),
+                },
+            );
+            let associativity = Operator::ALL
+                .iter()
+                .find(|op| op.precedence() == precedence)
+                .map(Operator::associativity);
+
+            level = match associativity {
+                Some(Associativity::Right) => parse_op_right(op, level),
+                _ => parse_op(op, level),
+            };
+        }
+
+        level
+    })
+    .labelled("parse_expr")
+    .boxed()
+}
+
+/// Emits an error if `target`, the left-hand side of an assignment, isn't an identifier, tuple
+/// access, or array access.
+fn validate_assign_target(target: &Expression, emit: &mut dyn FnMut(Error)) {
+    if !matches!(
+        target.kind(),
+        ExpressionKind::Identifier(_)
+            | ExpressionKind::TupleIndex { .. }
+            | ExpressionKind::TupleField { .. }
+            | ExpressionKind::ArrayIndex { .. }
+    ) {
+        emit(Error::general(
+            target.span.clone(),
+            "cannot assign to this expression",
+            Some("parse_expr.assign"),
+        ));
+    }
+}
+
+/// A single access applied after an expression by [`parse_postfix`].
+enum Postfix {
+    /// `.N`, or `None` if the index was rejected for not being constant.
+    TupleIndex(Option<usize>),
+    TupleField(Symbol),
+    ArrayIndex(Expression),
+}
+
+/// Applies any trailing `.N`/`.name` tuple accesses and `[i]` array accesses to `base`.
+/// Parenthesised tuple indices like `.(i)` are parsed only to be rejected, as tuple indices must be
+/// known at compile time.
+fn parse_postfix<'a>(
+    base: impl 'a + Parser<TokenKind, Expression, Error = Error>,
+    expr: impl 'a + Parser<TokenKind, Expression, Error = Error> + Clone,
+) -> AlgoParser<'a, Expression> {
+    let constant = choice((
+        parse_uinteger(),
+        parse_integer().try_map(|int, span| {
+            usize::try_from(int)
+                .map_err(|_| Error::general(span, "tuple index cannot be negative", None))
+        }),
+    ))
+    .map(|index| Postfix::TupleIndex(Some(index)));
+    let field = parse_symbol().map(Postfix::TupleField);
+    let non_constant = expr
+        .clone()
+        .delimited_by(just(TokenKind::GroupOpen), just(TokenKind::GroupClose))
+        .validate(|_, span, emit| {
+            emit(Error::general(
+                span,
+                "tuple index must be a constant integer",
+                Some("parse_postfix"),
+            ));
+
+            Postfix::TupleIndex(None)
+        });
+
+    let tuple_access = just(TokenKind::Dot).ignore_then(choice((constant, field, non_constant)));
+    let array_access = expr
+        .delimited_by(just(TokenKind::ArrayOpen), just(TokenKind::ArrayClose))
+        .map(Postfix::ArrayIndex);
+
+    base.then(
+        tuple_access
+            .or(array_access)
+            .map_with_span(|postfix, span: Span| (postfix, span))
+            .repeated(),
+    )
+    .foldl(|base, (postfix, postfix_span)| {
+        let span = base.span.start..postfix_span.end;
+        let kind = match postfix {
+            Postfix::TupleIndex(Some(index)) => ExpressionKind::TupleIndex {
+                tuple: Box::new(base),
+                index,
             },
-            conditional_flow,
-        );
-        let exponent = parse_op(select! { TokenKind::Exp => Operator::Exp }, condition);
-        let mul_div_rem = parse_op(
-            select! { TokenKind::Mul => Operator::Mul,
-            TokenKind::Div => Operator::Div,
-            TokenKind::Rem => Operator::Rem },
-            exponent,
-        );
-        let add_sub = parse_op(
-            select! { TokenKind::Add => Operator::Add, TokenKind::Sub => Operator::Sub},
-            mul_div_rem,
-        );
-        let bitshift = parse_op(
-            select! { TokenKind::Shr => Operator::Shr, TokenKind::Shl => Operator::Shl},
-            add_sub,
-        );
-        let bitand = parse_op(select! { TokenKind::BitAnd => Operator::BitAnd }, bitshift);
-        let bitxor = parse_op(select! { TokenKind::BitXor => Operator::BitXor}, bitand);
-        let bitor = parse_op(select! { TokenKind::BitOr => Operator::BitOr }, bitxor);
-        let comparison = parse_op(
-            select! {
-                TokenKind::Eq => Operator::Eq,
-                TokenKind::NotEq => Operator::NotEq,
-                TokenKind::Greater => Operator::Greater,
-                TokenKind::GreaterEq => Operator::GreaterEq,
-                TokenKind::Less => Operator::Less,
-                TokenKind::LessEq => Operator::LessEq,
+            Postfix::TupleIndex(None) => ExpressionKind::Error,
+            Postfix::TupleField(field) => ExpressionKind::TupleField {
+                tuple: Box::new(base),
+                field,
             },
-            bitor,
-        );
-        let logical_and = parse_op(
-            select! {
-                TokenKind::And => Operator::And
+            Postfix::ArrayIndex(index) => ExpressionKind::ArrayIndex {
+                array: Box::new(base),
+                index: Box::new(index),
             },
-            comparison,
-        );
-        let logical_xor = parse_op(select! { TokenKind::Xor => Operator::Xor}, logical_and);
-        parse_op(select! { TokenKind::Or => Operator::Or}, logical_xor)
+        };
+
+        expr!(kind, span)
     })
-    .labelled("parse_expr")
     .boxed()
 }
 
-fn parse_tuple<'a>() -> AlgoParser<'a, Expression> {
+fn parse_tuple<'a>(config: ParseConfig) -> AlgoParser<'a, Expression> {
     parse_expr()
         .separated_by(just(TokenKind::Separator))
         .at_least(1)
         .delimited_by(just(TokenKind::GroupOpen), just(TokenKind::GroupClose))
+        .validate(move |elements, span, emit| {
+            validate_arity(elements, span, emit, config.max_arity)
+        })
         .map_with_span(|expr, span| expr!(ExpressionKind::Tuple(expr), span))
         .labelled("parse_tuple")
         .boxed()
 }
 
-fn parse_array<'a>() -> AlgoParser<'a, Expression> {
+fn parse_array<'a>(config: ParseConfig) -> AlgoParser<'a, Expression> {
     parse_expr()
         .separated_by(just(TokenKind::Separator))
         .at_least(1)
         .delimited_by(just(TokenKind::ArrayOpen), just(TokenKind::ArrayClose))
+        .validate(move |elements, span, emit| {
+            validate_arity(elements, span, emit, config.max_arity)
+        })
         .map_with_span(|expr, span| expr!(ExpressionKind::Array(expr), span))
         .labelled("parse_array")
         .boxed()
 }
 
+/// Catches a type keyword written where a value is expected, e.g. `x: Int`, so it can be
+/// reported specifically rather than as an unexpected token.
+fn parse_type_as_value() -> impl Parser<TokenKind, Expression, Error = Error> {
+    select! {
+        token @ (
+            TokenKind::TypeInt
+            | TokenKind::TypeUInt
+            | TokenKind::TypeIntN(_)
+            | TokenKind::TypeUIntN(_)
+            | TokenKind::TypeFloat
+            | TokenKind::TypeStr
+            | TokenKind::TypeChar
+            | TokenKind::TypeBool
+        ) => token,
+    }
+    .validate(|token, span: Span, emit| {
+        emit(Error::general(
+            span.clone(),
+            &format!("`{token}` is a type, not a value"),
+            Some("parse_expr.type_as_value"),
+        ));
+
+        expr!(ExpressionKind::Error, span)
+    })
+}
+
+fn parse_literal() -> impl Parser<TokenKind, Expression, Error = Error> {
+    select! {
+        token @ TokenKind::TypeUnit => (ExpressionKind::Unit, token),
+        token @ TokenKind::Integer(int) => (ExpressionKind::Int(int), token),
+        token @ TokenKind::UInteger(uint) => (ExpressionKind::UInt(uint), token),
+        token @ TokenKind::Float(bits) => (ExpressionKind::Float(f64::from_bits(bits)), token),
+        token @ TokenKind::Boolean(bool) => (ExpressionKind::Bool(bool), token),
+        token @ TokenKind::StringLiteral(str) => (ExpressionKind::Str(str), token),
+        token @ TokenKind::CharLiteral(char) => (ExpressionKind::Char(char), token),
+    }
+    .map_with_span(|(kind, token), span| Expression {
+        kind,
+        span,
+        token: Some(token),
+    })
+    .labelled("parse_literal")
+}
+
 fn parse_integer() -> impl Parser<TokenKind, isize, Error = Error> {
     select! { TokenKind::Integer(x) => x }.labelled("parse_integer")
 }
@@ -376,6 +1182,644 @@ fn parse_symbol() -> impl Parser<TokenKind, Symbol, Error = Error> {
 
 #[cfg(test)]
 mod tests {
+    use super::{diff_ast, AstPathSegment, Expression, ExpressionKind};
+    use crate::{lexer::TokenKind, tests::parse_and_eq, types::Type, ErrorKind, Operator};
+    use chumsky::Parser;
+
+    fn parse_first(input: &str) -> Expression {
+        super::parse(crate::lexer::lex(input))
+            .unwrap()
+            .into_items()
+            .remove(0)
+    }
+
+    fn flow_from(expr: &Expression) -> &Expression {
+        match expr.kind() {
+            ExpressionKind::ControlFlow { from, into: None } => from,
+            kind => panic!("expected control flow, found {kind:?}"),
+        }
+    }
+
+    fn binary(expr: &Expression) -> (&Expression, Operator, &Expression) {
+        match expr.kind() {
+            ExpressionKind::Binary { lhs, op, rhs } => (lhs, *op, rhs),
+            kind => panic!("expected binary expression, found {kind:?}"),
+        }
+    }
+
+    #[test]
+    fn left_associative_chain() {
+        let expr = parse_first("1 - 2 - 3");
+        let (lhs, op, rhs) = binary(flow_from(&expr));
+
+        assert_eq!(op, Operator::Sub);
+        assert_eq!(rhs.kind(), &ExpressionKind::Int(3));
+
+        let (lhs, op, rhs) = binary(lhs);
+        assert_eq!(op, Operator::Sub);
+        assert_eq!(lhs.kind(), &ExpressionKind::Int(1));
+        assert_eq!(rhs.kind(), &ExpressionKind::Int(2));
+    }
+
+    #[test]
+    fn right_associative_chain() {
+        let expr = parse_first("2 ** 3 ** 4");
+        let (lhs, op, rhs) = binary(flow_from(&expr));
+
+        assert_eq!(op, Operator::Exp);
+        assert_eq!(lhs.kind(), &ExpressionKind::Int(2));
+
+        let (lhs, op, rhs) = binary(rhs);
+        assert_eq!(op, Operator::Exp);
+        assert_eq!(lhs.kind(), &ExpressionKind::Int(3));
+        assert_eq!(rhs.kind(), &ExpressionKind::Int(4));
+    }
+
+    #[test]
+    fn program_span() {
+        let program = super::parse(crate::lexer::lex("  1 + 2\n 3 => a  # trailing\n")).unwrap();
+
+        assert_eq!(program.items().len(), 2);
+        assert_eq!(program.span(), &(2..15));
+    }
+
+    fn parse_errors(input: &str) -> Vec<crate::Error> {
+        super::parse(crate::lexer::lex(input)).unwrap_err()
+    }
+
+    #[test]
+    fn recover_at_terminator() {
+        let errs = parse_errors("var a: () => 1 +;\nvar b: () => * 2;");
+
+        assert_eq!(errs.len(), 2);
+        assert_ne!(errs[0].span(), errs[1].span());
+    }
+
+    fn item_kinds(input: &str) -> Vec<ExpressionKind> {
+        super::parse(crate::lexer::lex(input))
+            .unwrap()
+            .into_items()
+            .into_iter()
+            .map(|item| flow_from(&item).kind().clone())
+            .collect()
+    }
+
+    #[test]
+    fn empty_statements() {
+        let one = vec![ExpressionKind::Int(1)];
+
+        assert_eq!(item_kinds(";1"), one);
+        assert_eq!(item_kinds("1;"), one);
+        assert_eq!(item_kinds(";;1;;"), one);
+        assert_eq!(item_kinds(";;"), vec![]);
+    }
+
+    #[test]
+    fn doubled_terminator() {
+        let program = super::parse(crate::lexer::lex("var a: () => 1;;\nvar b: () => 2;")).unwrap();
+
+        assert_eq!(program.items().len(), 2);
+        assert!(program
+            .items()
+            .iter()
+            .all(|item| matches!(item.kind(), ExpressionKind::VarDef { .. })));
+    }
+
+    #[test]
+    fn recovery_matrix() {
+        use TokenKind::{ArrayClose, ArrayOpen, BlockClose, BlockOpen, GroupClose, GroupOpen};
+
+        // Each statement is followed by a malformed one, which must still be reported.
+        let next = "\nvar z: () => * 2;";
+        let cases = [
+            ("(1, 2;", Err(GroupOpen)),
+            ("(1, 2];", Ok(ArrayClose)),
+            ("(1, 2));", Ok(GroupClose)),
+            ("[1, 2;", Err(ArrayOpen)),
+            ("[1, 2);", Ok(GroupClose)),
+            ("[1, 2]];", Ok(ArrayClose)),
+            ("{1 + 2;", Err(BlockOpen)),
+            ("{1 + 2);", Ok(GroupClose)),
+            ("{1 + 2}};", Ok(BlockClose)),
+        ];
+
+        // `Err(open)` expects an unclosed `open`, `Ok(close)` an unexpected `close`.
+        for (statement, expected) in cases {
+            let input = format!("{statement}{next}");
+            let errs = parse_errors(&input);
+
+            assert_eq!(errs.len(), 2, "errors for {statement:?}: {errs:?}");
+            match (expected, errs[0].kind()) {
+                (Err(open), ErrorKind::UnclosedDelimiter { delimiter, .. }) => {
+                    assert_eq!(*delimiter, open, "delimiter for {statement:?}");
+                }
+                (Ok(close), ErrorKind::Unexpected { found, .. }) => {
+                    assert_eq!(*found, Some(close), "found for {statement:?}");
+                    // The offending close is the last one in the statement.
+                    let close_start = statement.rfind([')', ']', '}']).unwrap();
+                    assert_eq!(errs[0].span().start, close_start, "span for {statement:?}");
+                }
+                (_, kind) => panic!("unexpected diagnostic for {statement:?}: {kind:?}"),
+            }
+
+            let next_start = input.find('*').unwrap();
+            assert_eq!(
+                errs[1].span(),
+                &(next_start..next_start + 1),
+                "recovery after {statement:?}"
+            );
+        }
+    }
+
+    fn reduce_first(input: &str) -> Result<ExpressionKind, crate::Error> {
+        parse_first(input)
+            .try_reduce()
+            .map(|expr| flow_from(&expr).kind().clone())
+    }
+
+    #[test]
+    fn fold_arithmetic() {
+        assert_eq!(
+            reduce_first("2 * (3 + 4)").unwrap(),
+            ExpressionKind::Int(14)
+        );
+        assert_eq!(
+            reduce_first("2 ** 10 - 1").unwrap(),
+            ExpressionKind::Int(1023)
+        );
+        assert_eq!(reduce_first("7 % 4 << 2").unwrap(), ExpressionKind::Int(12));
+    }
+
+    #[test]
+    fn fold_uint_exp() {
+        assert_eq!(
+            reduce_first("2U ** 10U").unwrap(),
+            ExpressionKind::UInt(1024)
+        );
+    }
+
+    #[test]
+    fn fold_uint_exp_overflow() {
+        let err = reduce_first("2U ** 64U").unwrap_err();
+
+        assert_eq!(err.span(), &(0..9));
+        assert!(matches!(
+            err.kind(),
+            ErrorKind::General(msg) if msg == "arithmetic overflow in constant expression"
+        ));
+    }
+
+    #[test]
+    fn fold_uint_exp_negative() {
+        // `!0` is the bitwise complement of zero, i.e. `-1`.
+        let err = reduce_first("2U ** !0").unwrap_err();
+
+        assert!(matches!(
+            err.kind(),
+            ErrorKind::General(msg) if msg == "exponent -1 is out of range for `UInt`"
+        ));
+    }
+
+    #[test]
+    fn fold_logic() {
+        assert_eq!(
+            reduce_first("true && false || true").unwrap(),
+            ExpressionKind::Bool(true)
+        );
+        assert_eq!(reduce_first("3 > 2").unwrap(), ExpressionKind::Bool(true));
+    }
+
+    #[test]
+    fn fold_partial() {
+        let expr = reduce_first("a + 2 * 3").unwrap();
+        let ExpressionKind::Binary { rhs, .. } = expr else {
+            panic!("expected a binary expression, found {expr:?}");
+        };
+
+        assert_eq!(rhs.kind(), &ExpressionKind::Int(6));
+        assert_eq!(rhs.span(), &(4..9));
+    }
+
+    #[test]
+    fn fold_division_by_zero() {
+        let err = reduce_first("1 / 0").unwrap_err();
+
+        assert_eq!(err.span(), &(0..5));
+        assert!(matches!(
+            err.kind(),
+            ErrorKind::General(msg) if msg == "division by zero"
+        ));
+        assert!(reduce_first("1 % 0").is_err());
+    }
+
+    #[test]
+    fn fold_oversized_shift() {
+        let err = reduce_first("1 << 64").unwrap_err();
+
+        assert!(matches!(
+            err.kind(),
+            ErrorKind::General(msg) if msg == "shift amount 64 is out of range for `Int`"
+        ));
+    }
+
+    #[test]
+    fn type_as_value() {
+        let errs = parse_errors("x: Int");
+
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].span(), &(3..6));
+        assert!(matches!(
+            errs[0].kind(),
+            ErrorKind::General(msg) if msg == "`Int` is a type, not a value"
+        ));
+    }
+
+    #[test]
+    fn type_in_annotation() {
+        assert!(super::parse(crate::lexer::lex("var a: (x: Int) => x;")).is_ok());
+    }
+
+    #[test]
+    fn fixed_width_annotation() {
+        let expr = parse_first("var a: (x: u16) => x;");
+
+        match expr.kind() {
+            ExpressionKind::VarDef { ty, .. } => assert_eq!(
+                ty,
+                &Type::Tuple(vec![(
+                    Some(crate::interned!("x")),
+                    Type::UInt(crate::types::IntWidth::W16)
+                )])
+            ),
+            kind => panic!("expected a var definition, found {kind:?}"),
+        }
+    }
+
+    #[test]
+    fn if_else() {
+        let expr = parse_first("if a { 1 } else { 2 }");
+
+        match flow_from(&expr).kind() {
+            ExpressionKind::If {
+                cond,
+                then,
+                otherwise: Some(otherwise),
+            } => {
+                assert!(matches!(cond.kind(), ExpressionKind::Identifier(_)));
+                assert_eq!(then.kind(), &ExpressionKind::Int(1));
+                assert_eq!(otherwise.kind(), &ExpressionKind::Int(2));
+            }
+            kind => panic!("expected an if/else, found {kind:?}"),
+        }
+        assert_eq!(flow_from(&expr).span(), &(0..21));
+    }
+
+    #[test]
+    fn if_without_else() {
+        let expr = parse_first("if a = b { () }");
+
+        assert!(matches!(
+            flow_from(&expr).kind(),
+            ExpressionKind::If {
+                otherwise: None,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn tuple_index() {
+        let expr = parse_first("a.1 + 2");
+        let (lhs, _, _) = binary(flow_from(&expr));
+
+        match lhs.kind() {
+            ExpressionKind::TupleIndex { tuple, index: 1 } => {
+                assert!(matches!(tuple.kind(), ExpressionKind::Identifier(_)));
+            }
+            kind => panic!("expected a tuple index, found {kind:?}"),
+        }
+        assert_eq!(lhs.span(), &(0..3));
+    }
+
+    #[test]
+    fn chained_tuple_index() {
+        let expr = parse_first("t.0.1");
+
+        match flow_from(&expr).kind() {
+            ExpressionKind::TupleIndex { tuple, index: 1 } => {
+                assert!(matches!(
+                    tuple.kind(),
+                    ExpressionKind::TupleIndex { index: 0, .. }
+                ));
+                assert_eq!(tuple.span(), &(0..3));
+            }
+            kind => panic!("expected a tuple index, found {kind:?}"),
+        }
+        assert_eq!(flow_from(&expr).span(), &(0..5));
+    }
+
+    #[test]
+    fn tuple_field_and_array_index() {
+        let expr = parse_first("[a.b][0]");
+
+        match flow_from(&expr).kind() {
+            ExpressionKind::ArrayIndex { array, index } => {
+                assert_eq!(index.kind(), &ExpressionKind::Int(0));
+                match array.kind() {
+                    ExpressionKind::Array(elements) => assert!(matches!(
+                        elements[0].kind(),
+                        ExpressionKind::TupleField { .. }
+                    )),
+                    kind => panic!("expected an array, found {kind:?}"),
+                }
+            }
+            kind => panic!("expected an array index, found {kind:?}"),
+        }
+        assert_eq!(flow_from(&expr).span(), &(0..8));
+    }
+
+    #[test]
+    fn tuple_index_non_constant() {
+        let errs = parse_errors("a.(x)");
+
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].span(), &(2..5));
+        assert!(matches!(
+            errs[0].kind(),
+            ErrorKind::General(msg) if msg == "tuple index must be a constant integer"
+        ));
+    }
+
+    #[test]
+    fn assign_to_identifier() {
+        assert!(super::parse(crate::lexer::lex("(a: 1, b: false)")).is_ok());
+    }
+
+    #[test]
+    fn assign_to_index() {
+        let expr = parse_first("a[0]: 1");
+        let (lhs, op, _) = binary(flow_from(&expr));
+
+        assert_eq!(op, Operator::Assign);
+        assert!(matches!(lhs.kind(), ExpressionKind::ArrayIndex { .. }));
+    }
+
+    #[test]
+    fn assign_to_field() {
+        for input in ["a.b: 1", "a.0: 1"] {
+            let expr = parse_first(input);
+            let (lhs, op, _) = binary(flow_from(&expr));
+
+            assert_eq!(op, Operator::Assign);
+            assert!(
+                matches!(
+                    lhs.kind(),
+                    ExpressionKind::TupleField { .. } | ExpressionKind::TupleIndex { .. }
+                ),
+                "target of {input:?}: {:?}",
+                lhs.kind()
+            );
+        }
+    }
+
+    #[test]
+    fn assign_to_literal() {
+        let errs = parse_errors("3: x");
+
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].span(), &(0..1));
+        assert!(matches!(
+            errs[0].kind(),
+            ErrorKind::General(msg) if msg == "cannot assign to this expression"
+        ));
+    }
+
+    #[test]
+    fn assign_to_binary() {
+        let errs = parse_errors("1 + (a + b): x");
+
+        // Assignment binds loosest, so the whole sum is the target.
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].span(), &(0..10));
+    }
+
+    #[test]
+    fn precedence_table_matches_parser() {
+        use crate::Associativity;
+
+        let ops = [
+            "**", "+", "-", "*", "/", "%", ">>", "<<", "^", "&", "|", "=", "!=", ">", ">=", "<",
+            "<=", "||", "^^", "&&", "?>", "??", ":",
+        ];
+        let op = |text: &str| Operator::try_from(&crate::lexer::lex(text).next().unwrap().0);
+        // Invalid assignment targets are still parsed, so compare trees regardless of errors.
+        let parse = |input: &str| {
+            super::parse_expr()
+                .parse_recovery(crate::lexer::lex(input))
+                .0
+                .unwrap()
+        };
+
+        assert_eq!(ops.len(), Operator::ALL.len());
+        for a in ops {
+            for b in ops {
+                let (a_op, b_op) = (op(a).unwrap(), op(b).unwrap());
+                let binds_left = a_op.precedence() > b_op.precedence()
+                    || (a_op.precedence() == b_op.precedence()
+                        && a_op.associativity() == Associativity::Left);
+                let expected = if binds_left {
+                    format!("(x {a} y) {b} z")
+                } else {
+                    format!("x {a} (y {b} z)")
+                };
+
+                let input = format!("x {a} y {b} z");
+                let parsed = parse(&input);
+                assert_eq!(parsed.span(), &(0..input.len()), "span of {input:?}");
+                assert_eq!(
+                    diff_ast(&parsed, &parse(&expected)),
+                    None,
+                    "{input:?} should parse as {expected:?}"
+                );
+            }
+        }
+    }
+
+    fn tuple_source(arity: usize) -> String {
+        format!("({})", vec!["1"; arity].join(", "))
+    }
+
+    fn array_source(arity: usize) -> String {
+        format!("[{}]", vec!["1"; arity].join(", "))
+    }
+
+    fn max_arity() -> usize {
+        super::ParseConfig::default().max_arity
+    }
+
+    #[test]
+    fn tuple_max_arity() {
+        assert!(super::parse(crate::lexer::lex(&tuple_source(max_arity()))).is_ok());
+    }
+
+    #[test]
+    fn tuple_over_max_arity() {
+        let errs = parse_errors(&tuple_source(max_arity() + 1));
+
+        assert_eq!(errs.len(), 1);
+        assert!(matches!(
+            errs[0].kind(),
+            ErrorKind::General(msg) if msg.starts_with("too many elements")
+        ));
+    }
+
+    #[test]
+    fn array_max_arity() {
+        assert!(super::parse(crate::lexer::lex(&array_source(max_arity()))).is_ok());
+    }
+
+    #[test]
+    fn array_over_max_arity() {
+        let errs = parse_errors(&array_source(max_arity() + 1));
+
+        assert_eq!(errs.len(), 1);
+        assert!(matches!(
+            errs[0].kind(),
+            ErrorKind::General(msg) if msg.starts_with("too many elements")
+        ));
+    }
+
+    #[test]
+    fn configured_max_arity() {
+        let config = super::ParseConfig { max_arity: 2 };
+        let parse = |input: &str| super::parse_with(crate::lexer::lex(input), config);
+
+        assert!(parse("(1, 2); [1, 2]").is_ok());
+        for input in ["(1, 2, 3)", "[1, 2, 3]"] {
+            let errs = parse(input).unwrap_err();
+
+            assert_eq!(errs.len(), 1, "errors for {input:?}");
+            assert!(matches!(
+                errs[0].kind(),
+                ErrorKind::General(msg) if msg == "too many elements (maximum is 2)"
+            ));
+        }
+    }
+
+    #[test]
+    fn array_type_zero_len() {
+        parse_and_eq(
+            "[Int, 0]",
+            super::parse_array_type(),
+            &Type::Array {
+                ty: Box::new(Type::INT),
+                len: Some(0),
+            },
+        );
+    }
+
+    #[test]
+    fn array_type_negative_len() {
+        let errs = super::parse_array_type()
+            .parse(crate::lexer::lex("[Int, !0]"))
+            .unwrap_err();
+
+        assert!(errs.iter().any(|err| matches!(
+            err.kind(),
+            ErrorKind::General(msg) if msg == "array len cannot be negative"
+        )));
+    }
+
+    #[test]
+    fn array_type_overflowing_len() {
+        let errs = parse_errors("var a: (xs: [Int, 99999999999999999999999]) => xs;");
+
+        assert_eq!(errs.len(), 1);
+        assert!(matches!(
+            errs[0].kind(),
+            ErrorKind::General(msg) if msg == "integer literal out of range"
+        ));
+    }
+
+    #[test]
+    fn literal_token() {
+        let expr = parse_first("12345U");
+        assert_eq!(
+            flow_from(&expr).literal_token(),
+            Some(&TokenKind::UInteger(12345))
+        );
+
+        let expr = parse_first("a");
+        assert_eq!(flow_from(&expr).literal_token(), None);
+    }
+
+    #[test]
+    fn parse_with_tokens() {
+        let input = "1 + 2 * a";
+        let (tokens, program) = super::parse_with_tokens(input);
+
+        assert_eq!(tokens, crate::lexer::lex(input).collect::<Vec<_>>());
+        assert!(program.is_ok());
+    }
+
+    #[test]
+    fn parse_with_tokens_error() {
+        let input = "1 + + 2";
+        let (tokens, program) = super::parse_with_tokens(input);
+
+        assert_eq!(tokens, crate::lexer::lex(input).collect::<Vec<_>>());
+        assert_eq!(tokens.len(), 4);
+        assert!(program.is_err());
+    }
+
+    #[test]
+    fn node_span_binary() {
+        let expr = parse_first("a * 2 + 1");
+
+        let (lhs, _, _) = binary(flow_from(&expr));
+
+        assert_eq!(super::node_span(flow_from(&expr)), 0..9);
+        assert_eq!(super::node_span(lhs), 0..5);
+    }
+
+    #[test]
+    fn node_span_vardef() {
+        let program = super::parse(crate::lexer::lex("var f: (x: Int) => x + 1;")).unwrap();
+
+        assert_eq!(super::node_span(&program.items()[0]), 0..25);
+    }
+
+    #[test]
+    fn diff_identical() {
+        assert_eq!(
+            diff_ast(&parse_first("1 + 2 * 3"), &parse_first("1+2*3")),
+            None
+        );
+    }
+
+    #[test]
+    fn diff_divergence_path() {
+        let diff = diff_ast(&parse_first("1 + 2 * 3"), &parse_first("1 + 2 * 4")).unwrap();
+
+        assert_eq!(
+            diff.path(),
+            [
+                AstPathSegment::From,
+                AstPathSegment::Rhs,
+                AstPathSegment::Rhs
+            ]
+        );
+        assert_eq!(diff.lhs_span(), &(8..9));
+        assert_eq!(diff.rhs_span(), &(8..9));
+    }
+
+    #[test]
+    fn diff_element_count() {
+        let diff = diff_ast(&parse_first("[1, 2]"), &parse_first("[1, 2, 3]")).unwrap();
+
+        assert_eq!(diff.path(), [AstPathSegment::From]);
+    }
+
     // use crate::{interned, tests::parse_and_eq, Operator};
 
     // use super::Expression;
@@ -0,0 +1,149 @@
+//! Memoized compilation queries, so tooling can re-query an unchanged source without recompiling.
+//!
+//! Each query caches its result until the source changes, at which point every downstream query is
+//! invalidated and recomputed on its next request.
+
+use crate::{lexer::Tokens, parser::Program, types::TypedExpression, Error};
+use std::rc::Rc;
+
+pub type ParseResult = Rc<Result<Program, Vec<Error>>>;
+pub type TypeResult = Rc<Result<Vec<TypedExpression>, Vec<Error>>>;
+
+/// Number of times each query has actually been computed (rather than served from cache).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Executions {
+    pub lex: usize,
+    pub parse: usize,
+    pub type_check: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct Database {
+    source: String,
+    tokens: Option<Tokens>,
+    program: Option<ParseResult>,
+    typed: Option<TypeResult>,
+    executions: Executions,
+}
+
+impl Database {
+    pub fn new(source: &str) -> Self {
+        Self {
+            source: source.to_owned(),
+            ..Self::default()
+        }
+    }
+
+    #[inline]
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    #[inline]
+    pub const fn executions(&self) -> Executions {
+        self.executions
+    }
+
+    /// Replaces the source text, invalidating all cached queries if it changed.
+    pub fn set_source(&mut self, source: &str) {
+        if self.source != source {
+            source.clone_into(&mut self.source);
+            self.tokens = None;
+            self.program = None;
+            self.typed = None;
+        }
+    }
+
+    pub fn tokens(&mut self) -> &Tokens {
+        if self.tokens.is_none() {
+            self.executions.lex += 1;
+        }
+
+        self.tokens
+            .get_or_insert_with(|| crate::lexer::lex(&self.source))
+    }
+
+    pub fn parse(&mut self) -> ParseResult {
+        if let Some(program) = &self.program {
+            return Rc::clone(program);
+        }
+
+        let tokens = self.tokens().clone();
+        self.executions.parse += 1;
+
+        let program = Rc::new(crate::parser::parse(tokens));
+        self.program = Some(Rc::clone(&program));
+        program
+    }
+
+    pub fn type_check(&mut self) -> TypeResult {
+        if let Some(typed) = &self.typed {
+            return Rc::clone(typed);
+        }
+
+        let program = self.parse();
+        self.executions.type_check += 1;
+
+        let typed = Rc::new(match program.as_ref() {
+            Ok(program) => crate::types::type_exprs(program.items().to_vec()),
+            Err(errs) => Err(errs.clone()),
+        });
+        self.typed = Some(Rc::clone(&typed));
+        typed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Database, Executions};
+
+    #[test]
+    fn unchanged_is_cached() {
+        let mut db = Database::new("1 => true");
+
+        assert!(db.type_check().is_ok());
+        assert!(db.type_check().is_ok());
+        db.set_source("1 => true");
+        assert!(db.parse().is_ok());
+
+        assert_eq!(
+            db.executions(),
+            Executions {
+                lex: 1,
+                parse: 1,
+                type_check: 1
+            }
+        );
+    }
+
+    #[test]
+    fn unresolved_names() {
+        let mut db = Database::new("a + 1");
+
+        let typed = db.type_check();
+        let errs = typed.as_ref().as_ref().unwrap_err();
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].span(), &(0..1));
+
+        assert!(db.type_check().is_err());
+        assert_eq!(db.executions().type_check, 1);
+    }
+
+    #[test]
+    fn changed_recomputes() {
+        let mut db = Database::new("1");
+        assert!(db.type_check().is_ok());
+
+        db.set_source("1 =>");
+        assert!(db.type_check().is_err());
+
+        assert_eq!(
+            db.executions(),
+            Executions {
+                lex: 2,
+                parse: 2,
+                type_check: 2
+            }
+        );
+    }
+}
@@ -0,0 +1,153 @@
+//! Helpers for working with byte-offset [`Span`]s against the source they were lexed from.
+
+use crate::Span;
+
+/// Widens `span` to the nearest UTF-8 character boundaries in `source`, clamping it to the source
+/// length. Spans that already fall on character boundaries are returned unchanged.
+pub fn snap_to_char_boundary(source: &str, span: &Span) -> Span {
+    let mut start = span.start.min(source.len());
+    while !source.is_char_boundary(start) {
+        start -= 1;
+    }
+
+    let mut end = span.end.clamp(start, source.len());
+    while !source.is_char_boundary(end) {
+        end += 1;
+    }
+
+    start..end
+}
+
+/// Slices `source` by `span` without panicking on spans that split a multi-byte character.
+pub fn slice<'a>(source: &'a str, span: &Span) -> &'a str {
+    &source[snap_to_char_boundary(source, span)]
+}
+
+/// Whether `offset` falls within `span`. Spans are half-open, except that a zero-length span
+/// contains its own offset, so a cursor position can be matched against it.
+pub fn contains(span: &Span, offset: usize) -> bool {
+    if span.is_empty() {
+        offset == span.start
+    } else {
+        span.contains(&offset)
+    }
+}
+
+/// Whether `a` and `b` share at least one offset, following the same rules as [`contains`].
+pub fn overlaps(a: &Span, b: &Span) -> bool {
+    if a.is_empty() {
+        contains(b, a.start)
+    } else if b.is_empty() {
+        contains(a, b.start)
+    } else {
+        a.start < b.end && b.start < a.end
+    }
+}
+
+/// The smallest span covering both `a` and `b`, including any gap between them.
+pub fn merge(a: &Span, b: &Span) -> Span {
+    a.start.min(b.start)..a.end.max(b.end)
+}
+
+/// The 1-based `(line, column)` of the start of `span` in `source`, with the column counted in
+/// characters rather than bytes.
+///
+/// A span starting on a line break is placed at the end of the line it terminates, and a `\r\n`
+/// pair counts as a single break.
+pub fn line_col(source: &str, span: &Span) -> (usize, usize) {
+    let start = snap_to_char_boundary(source, &(span.start..span.start)).start;
+    let before = &source[..start];
+
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    let mut line = &before[line_start..];
+    if source[start..].starts_with('\n') {
+        line = line.strip_suffix('\r').unwrap_or(line);
+    }
+
+    (before.matches('\n').count() + 1, line.chars().count() + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{contains, line_col, merge, overlaps, slice, snap_to_char_boundary};
+
+    #[test]
+    fn snap_multibyte() {
+        // `é` occupies bytes 1..3, so both span edges land mid-character.
+        let source = "aé b";
+
+        assert_eq!(snap_to_char_boundary(source, &(2..3)), 1..3);
+        assert_eq!(slice(source, &(2..3)), "é");
+    }
+
+    #[test]
+    fn snap_out_of_bounds() {
+        assert_eq!(slice("abc", &(1..10)), "bc");
+        assert_eq!(slice("abc", &(10..12)), "");
+    }
+
+    #[test]
+    fn snap_aligned() {
+        assert_eq!(slice("aé b", &(0..3)), "aé");
+    }
+
+    #[test]
+    fn contains_boundaries() {
+        assert!(contains(&(2..5), 2));
+        assert!(contains(&(2..5), 4));
+        assert!(!contains(&(2..5), 5));
+        assert!(!contains(&(2..5), 1));
+    }
+
+    #[test]
+    fn contains_zero_length() {
+        assert!(contains(&(3..3), 3));
+        assert!(!contains(&(3..3), 4));
+    }
+
+    #[test]
+    fn overlapping_spans() {
+        assert!(overlaps(&(0..4), &(3..6)));
+        assert!(overlaps(&(0..6), &(2..3)));
+        assert!(!overlaps(&(0..3), &(3..6)));
+        assert!(!overlaps(&(0..2), &(4..6)));
+
+        assert!(overlaps(&(0..4), &(2..2)));
+        assert!(!overlaps(&(4..4), &(0..4)));
+    }
+
+    #[test]
+    fn merge_spans() {
+        assert_eq!(merge(&(4..6), &(0..2)), 0..6);
+        assert_eq!(merge(&(0..6), &(2..3)), 0..6);
+    }
+
+    #[test]
+    fn line_col_multiline() {
+        let source = "var a: 1;\nvar bé: 2;\n\nc";
+
+        assert_eq!(line_col(source, &(0..3)), (1, 1));
+        assert_eq!(line_col(source, &(4..5)), (1, 5));
+        assert_eq!(line_col(source, &(14..15)), (2, 5));
+        // `é` is two bytes but a single column.
+        assert_eq!(line_col(source, &(17..18)), (2, 7));
+        assert_eq!(line_col(source, &(23..24)), (4, 1));
+    }
+
+    #[test]
+    fn line_col_line_break() {
+        let source = "ab\ncd";
+
+        assert_eq!(line_col(source, &(2..3)), (1, 3));
+        assert_eq!(line_col(source, &(3..4)), (2, 1));
+    }
+
+    #[test]
+    fn line_col_crlf() {
+        let source = "ab\r\ncd";
+
+        assert_eq!(line_col(source, &(2..3)), (1, 3));
+        assert_eq!(line_col(source, &(3..4)), (1, 3));
+        assert_eq!(line_col(source, &(5..6)), (2, 2));
+    }
+}
@@ -1,216 +1,166 @@
-mod optimizer;
-pub use optimizer::*;
-
 mod nodes;
 pub use nodes::*;
 
 use crate::{
-    parser::{Expression, HeapExpr, SpannedExpr},
-    strings::Symbol,
-    types::Type,
-    Error, Operator,
+    parser::{Expression, ExpressionKind},
+    Error,
 };
-use std::collections::BTreeMap;
-
-type Scope = Vec<(Symbol, Binding)>;
-
-fn stratify_exprs(
-    ast: Vec<HeapExpr>,
-) -> Result<(SpannedExpr, BTreeMap<Symbol, (Type, SpannedExpr)>), Vec<Error>> {
-    // TLE, or `Top Level Expression`
-    let (defs, tles) =
-        ast.into_iter()
-            .fold((Defs::new(), Vec::new()), |(mut defs, mut tles), expr| {
-                let (expr, span) = *expr;
-                match expr {
-                    Expression::VarDef { name, in_ty, expr } => {
-                        assert_eq!(defs.insert(name, (in_ty, *expr)), None);
-                    }
-
-                    expr if !expr.is_def() => tles.push((expr, span)),
-
-                    _ => panic!("failed to stratify all def expressions"),
-                }
 
-                (defs, tles)
-            });
-
-    let mut tles_iter = tles.into_iter();
-    let tle = {
-        match tles_iter.len() {
-            1 => tles_iter.next().unwrap(),
-            0 => return Err(vec![Error::no_top_level_expr()]),
-            _ => {
-                return Err(tles_iter
-                    .map(|(_, span)| {
-                        Error::general(
-                            span,
-                            "cannot have multiple top-level expressions",
-                            Some("parse_input.tle_check"),
-                        )
-                    })
-                    .collect())
-            }
-        }
-    };
+/// Lowers an arithmetic or boolean expression tree into SSA form.
+///
+/// # Errors
+///
+/// Returns an error spanning the first node that isn't a literal, identifier, or binary operation.
+pub fn lower(expr: &Expression) -> Result<Function, Error> {
+    let mut builder = Builder::default();
+    let result = lower_expr(expr, &mut builder)?;
 
-    Ok((tle, defs))
+    Ok(builder.finish(result))
 }
 
-pub fn translate(ast: Vec<HeapExpr>) -> Result<Nodes, Vec<Error>> {
-    for expr in ast {
-        match *expr {}
+fn lower_expr(expr: &Expression, builder: &mut Builder) -> Result<ValueId, Error> {
+    let constant = |constant| InstructionKind::Const(constant);
 
-        let (nodes, scope) = bind_def(*expr)?;
-    }
+    Ok(match expr.kind() {
+        ExpressionKind::Int(int) => builder.push(constant(Constant::Int(*int))),
+        ExpressionKind::UInt(uint) => builder.push(constant(Constant::UInt(*uint))),
+        ExpressionKind::Float(float) => builder.push(constant(Constant::Float(float.to_bits()))),
+        ExpressionKind::Bool(bool) => builder.push(constant(Constant::Bool(*bool))),
 
-    match bind_expr(&tle, &mut nodes, &mut scope, &mut defs) {
-        Ok(binding) => {
-            nodes.bind_push(Node::Return(binding));
-            Ok(nodes)
-        }
+        ExpressionKind::Identifier(name) => builder.param(*name),
 
-        Err(err) => Err(vec![err]),
-    }
+        ExpressionKind::Binary { lhs, op, rhs } => {
+            // Operands are lowered first, so every instruction follows its dependencies.
+            let lhs = lower_expr(lhs, builder)?;
+            let rhs = lower_expr(rhs, builder)?;
 
-    todo!()
-}
+            builder.push(InstructionKind::Binary { op: *op, lhs, rhs })
+        }
 
-fn bind_def(
-    expr: SpannedExpr,
-    ty: Type,
-    outer_scope: &mut Scope,
-) -> Result<(Nodes, Scope), Vec<Error>> {
-    let mut nodes = Nodes::new();
-    let mut scope = match ty {
-        Type::Tuple(params) => params
-            .into_iter()
-            .map(|(name, ty)| (name, nodes.bind_push(Node::Parameter(ty))))
-            .collect(),
-
-        // Unit is acceptable as a parameter grouping, but requires no scoping variables.
-        Type::Unit => Scope::new(),
-
-        // Top-level definitions accept no other parameter grouping types.
-        _ => unreachable!(),
-    };
-
-    match bind_expr(&expr, &mut nodes, &mut scope) {
-        Ok(_) => Ok((nodes, scope)),
-        Err(err) => Err(vec![err]),
-    }
+        // Flowing into another expression needs calls, which the SSA form doesn't model yet.
+        ExpressionKind::ControlFlow { from, into: None } => lower_expr(from, builder)?,
+
+        _ => {
+            return Err(Error::general(
+                expr.span().clone(),
+                "cannot lower this expression to SSA",
+                Some("ssa::lower"),
+            ))
+        }
+    })
 }
 
-fn bind_expr(expr: &SpannedExpr, nodes: &mut Nodes, scope: &mut Scope) -> Result<Binding, Error> {
-    // mark scope start
-    let scope_mark = scope.len();
-
-    let result = {
-        match &expr.0 {
-            Expression::Binary { lhs, op, rhs } => {
-                // TODO uninitialized variable error
-                let lhs_binding = bind_expr(lhs.as_ref(), nodes, scope, defs)?;
-                let rhs_binding = bind_expr(rhs.as_ref(), nodes, scope, defs)?;
-
-                match op {
-                    Operator::Add => Ok({
-                        let new_node = Node::Add(lhs_binding, rhs_binding);
-                        nodes.bind_push(new_node)
-                    }),
-                    Operator::Sub => Ok({
-                        let new_node = Node::Sub(lhs_binding, rhs_binding);
-                        nodes.bind_push(new_node)
-                    }),
-
-                    _ => unimplemented!(),
-                }
-            }
+#[cfg(test)]
+mod tests {
+    use super::{lower, Constant, Function, InstructionKind};
+    use crate::{interned, Operator};
 
-            Expression::Primitive(primitive) => Ok({
-                let new_node = Node::Bind(*primitive);
-                nodes.bind_push(new_node)
-            }),
+    fn lower_first(input: &str) -> Function {
+        let program = crate::parser::parse(crate::lexer::lex(input)).unwrap();
 
-            // TODO figure out how to model passing arguments to expressions
-            Expression::Identifier(symbol) => {
-                let binding = match find_binding(*symbol, scope) {
-                    Some(binding) => Ok(binding),
-                    None => bind_def(*symbol, nodes, defs),
-                }?;
+        lower(&program.into_items()[0]).unwrap()
+    }
 
-                let bound_node = nodes.get(binding).unwrap();
+    #[test]
+    fn dependency_order() {
+        let function = lower_first("a + b * c");
+        let [(_, a), (_, b), (_, c)] = function.params() else {
+            panic!("expected three params, found {:?}", function.params());
+        };
+        let [mul, add] = function.instructions() else {
+            panic!(
+                "expected two instructions, found {:?}",
+                function.instructions()
+            );
+        };
+
+        assert_eq!(
+            function
+                .params()
+                .iter()
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>(),
+            [interned!("a"), interned!("b"), interned!("c")]
+        );
+        assert_eq!(
+            mul.kind(),
+            &InstructionKind::Binary {
+                op: Operator::Mul,
+                lhs: *b,
+                rhs: *c
             }
-
-            Expression::Flow {
-                from: expr,
-                to: next,
-            } => {
-                // TODO maintain scope changes from `bind_from`
-                let bind_from = bind_expr(expr.as_ref(), nodes, scope, defs)?;
-                let bind_to = next
-                    .as_ref()
-                    .map(|expr| bind_expr(expr.as_ref(), nodes, scope, defs));
-
-                bind_to.unwrap_or(Ok(bind_from))
+        );
+        assert_eq!(
+            add.kind(),
+            &InstructionKind::Binary {
+                op: Operator::Add,
+                lhs: *a,
+                rhs: mul.result()
             }
+        );
+        assert_eq!(function.result(), add.result());
+    }
 
-            Expression::Control { exprs } => exprs
-                .iter()
-                .map(|expr| bind_expr(expr, nodes, scope, defs))
-                .last()
-                .unwrap(),
+    #[test]
+    fn value_numbering() {
+        let function = lower_first("a * 2 + a * 2");
+        let [two, mul, add] = function.instructions() else {
+            panic!(
+                "expected three instructions, found {:?}",
+                function.instructions()
+            );
+        };
+
+        assert_eq!(function.params().len(), 1);
+        assert_eq!(two.kind(), &InstructionKind::Const(Constant::Int(2)));
+        assert_eq!(
+            add.kind(),
+            &InstructionKind::Binary {
+                op: Operator::Add,
+                lhs: mul.result(),
+                rhs: mul.result()
+            }
+        );
+    }
 
-            expr => unimplemented!("no SSA path: {:?}", expr),
-        }
-    };
-    // return to scope start
-    scope.drain(scope_mark..);
+    #[test]
+    fn constants() {
+        let function = lower_first("true || false");
 
-    result
-}
+        assert_eq!(
+            function
+                .instructions()
+                .iter()
+                .map(|instruction| *instruction.kind())
+                .collect::<Vec<_>>(),
+            [
+                InstructionKind::Const(Constant::Bool(true)),
+                InstructionKind::Const(Constant::Bool(false)),
+                InstructionKind::Binary {
+                    op: Operator::Or,
+                    lhs: function.instructions()[0].result(),
+                    rhs: function.instructions()[1].result()
+                }
+            ]
+        );
+    }
 
-fn find_binding(symbol: Symbol, scopes: &mut Scope) -> Option<Binding> {
-    scopes.iter().rev().find_map(|(other, binding)| {
-        if symbol.eq(other) {
-            Some(binding.clone())
-        } else {
-            None
+    #[test]
+    fn unsupported_expressions() {
+        let cases = [
+            ("1 => a", 0..6),
+            ("var a: () => 1;", 0..15),
+            ("(1, 2)", 0..6),
+            ("[1, 2]", 0..6),
+            ("a + b.0", 4..7),
+            ("if a { 1 } else { 2 }", 0..21),
+        ];
+
+        for (input, span) in cases {
+            let program = crate::parser::parse(crate::lexer::lex(input)).unwrap();
+            let err = lower(&program.into_items()[0]).unwrap_err();
+
+            assert_eq!(err.span(), &span, "span for {input:?}");
         }
-    })
-}
-
-#[cfg(test)]
-mod tests {
-    // use crate::{
-    //     interned,
-    //     ssa::{bind_def, stratify_exprs, Binding, Node},
-    //     types::Type,
-    // };
-
-    // #[test]
-    // fn def_to_bindings() {
-    //     static SCRIPT: &str = "
-    //     var add_one: (a: Int) => a + 1;
-    //     1 => add_one
-    //     ";
-
-    //     let ast = crate::parser::parse(crate::lexer::lex(SCRIPT)).unwrap();
-    //     let (_, mut defs) = stratify_exprs(ast).unwrap();
-
-    //     let mut nodes = Vec::new();
-    //     let def = bind_def(&interned!("add_one"), &mut nodes, &mut defs).unwrap();
-    //     assert_eq!(def, Binding::generate(3));
-    //     assert_eq!(
-    //         nodes,
-    //         vec![
-    //             Node::Parameter(Type::Int),
-    //             Node::Bind(crate::Primitive::Int(1)),
-    //             Node::Add(Binding::generate(0), Binding::generate(1)),
-    //             Node::Expression(
-    //                 Binding::generate(0),
-    //                 vec![(interned!("a"), Binding::generate(0))]
-    //             )
-    //         ]
-    //     );
-    // }
+    }
 }
@@ -1,199 +1,129 @@
-use crate::{types::Type, Primitive};
-use std::{
-    cell::{Ref, RefCell, RefMut},
-    collections::HashMap,
-};
+use crate::{strings::Symbol, Operator};
 
+/// The number of a value within a [`Function`], in definition order.
 #[repr(transparent)]
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct Binding(usize);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ValueId(usize);
 
-impl Binding {
+impl ValueId {
     #[inline]
-    pub fn generate() -> Self {
-        Self(fastrand::usize(..))
+    pub const fn index(self) -> usize {
+        self.0
     }
 }
 
-pub enum NodeIndexer {
-    Binding(Binding),
-    Index(usize),
+impl core::fmt::Display for ValueId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "%{}", self.0)
+    }
 }
 
-pub trait NodeIndex {
-    fn into_indexer(self) -> NodeIndexer;
+/// A constant operand. Floats are stored as their bit pattern so constants stay `Eq` and `Hash`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Constant {
+    Int(isize),
+    UInt(usize),
+    Float(u64),
+    Bool(bool),
 }
 
-impl NodeIndex for Binding {
-    fn into_indexer(self) -> NodeIndexer {
-        NodeIndexer::Binding(self)
-    }
-}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InstructionKind {
+    Const(Constant),
 
-impl NodeIndex for usize {
-    fn into_indexer(self) -> NodeIndexer {
-        NodeIndexer::Index(self)
-    }
+    Binary {
+        op: Operator,
+        lhs: ValueId,
+        rhs: ValueId,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum Node {
-    Bind(Primitive),
-    Add(Binding, Binding),
-    Sub(Binding, Binding),
-
-    Parameter(Type),
-    Call(Binding),
-    Jump(Binding),
-    Expression(super::Params),
-    Return(Binding),
+pub struct Instruction {
+    result: ValueId,
+    kind: InstructionKind,
 }
 
-impl Node {
-    pub fn simplify(&mut self, nodes: &Nodes) {
-        match self {
-            Self::Add(lhs, rhs) => {
-                if let (Some(Self::Bind(lhs)), Some(Self::Bind(rhs))) = (
-                    nodes.get(lhs.clone()).as_deref(),
-                    nodes.get(rhs.clone()).as_deref(),
-                ) {
-                    if let Some(sum) = *lhs + *rhs {
-                        *self = Node::Bind(sum);
-                    }
-                }
-            }
-
-            Self::Sub(lhs, rhs) => {
-                if let (Some(Self::Bind(lhs)), Some(Self::Bind(rhs))) = (
-                    nodes.get(lhs.clone()).as_deref(),
-                    nodes.get(rhs.clone()).as_deref(),
-                ) {
-                    if let Some(sum) = *lhs - *rhs {
-                        *self = Node::Bind(sum);
-                    }
-                }
-            }
-
-            _ => {}
-        }
+impl Instruction {
+    #[inline]
+    pub const fn result(&self) -> ValueId {
+        self.result
     }
-}
 
-type NodeRef<'a> = (&'a Binding, Ref<'a, Node>);
-type NodeMut<'a> = (&'a Binding, RefMut<'a, Node>);
+    #[inline]
+    pub const fn kind(&self) -> &InstructionKind {
+        &self.kind
+    }
+}
 
-pub struct Nodes {
-    cache: HashMap<Binding, RefCell<Node>>,
-    composition: Vec<Binding>,
+/// Linear three-address code, where every value is defined exactly once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Function {
+    /// Free identifiers, which are the function's inputs.
+    params: Vec<(Symbol, ValueId)>,
+    instructions: Vec<Instruction>,
+    result: ValueId,
 }
 
-impl Nodes {
-    pub fn new() -> Self {
-        Self {
-            cache: HashMap::new(),
-            composition: Vec::new(),
-        }
+impl Function {
+    #[inline]
+    pub fn params(&self) -> &[(Symbol, ValueId)] {
+        &self.params
     }
 
     #[inline]
-    pub fn len(&self) -> usize {
-        self.composition.len()
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
     }
 
+    /// The value the function evaluates to.
     #[inline]
-    pub fn is_empty(&self) -> bool {
-        self.composition.is_empty()
+    pub const fn result(&self) -> ValueId {
+        self.result
     }
+}
 
-    pub fn bind_push(&mut self, node: Node) -> Binding {
-        let binding = Binding::generate();
-        assert!(self
-            .cache
-            .insert(binding.clone(), RefCell::new(node))
-            .is_none());
-        self.composition.push(binding.clone());
+/// Builds a [`Function`], numbering values so identical instructions are only emitted once.
+#[derive(Default)]
+pub(super) struct Builder {
+    params: Vec<(Symbol, ValueId)>,
+    instructions: Vec<Instruction>,
+    numbering: std::collections::HashMap<InstructionKind, ValueId>,
+}
 
-        binding
+impl Builder {
+    fn next_id(&self) -> ValueId {
+        ValueId(self.params.len() + self.instructions.len())
     }
 
-    fn binding_index<Idx: NodeIndex>(&self, index: Idx) -> Option<Binding> {
-        match index.into_indexer() {
-            NodeIndexer::Binding(binding) => Some(binding),
-            NodeIndexer::Index(index) => self.composition.get(index).map(Binding::clone),
+    pub(super) fn param(&mut self, name: Symbol) -> ValueId {
+        if let Some((_, id)) = self.params.iter().find(|(param, _)| *param == name) {
+            return *id;
         }
-    }
 
-    pub fn get<Idx: NodeIndex>(&self, index: Idx) -> Option<Ref<Node>> {
-        self.cache
-            .get(&self.binding_index(index)?)
-            .map(RefCell::borrow)
-    }
-
-    pub fn get_mut<Idx: NodeIndex>(&self, index: Idx) -> Option<RefMut<Node>> {
-        self.cache
-            .get(&self.binding_index(index)?)
-            .map(RefCell::borrow_mut)
-    }
-
-    pub fn remove<Idx: NodeIndex>(&mut self, index: Idx) {
-        self.binding_index(index)
-            .and_then(|binding| self.cache.remove(&binding))
-            .expect("index not found");
-    }
-
-    pub fn iter(&self) -> impl Iterator<Item = NodeRef> {
-        self.composition
-            .iter()
-            .map(|binding| (binding, self.get(binding.clone()).unwrap()))
-            .into_iter()
-    }
-
-    pub fn iter_mut(&self) -> impl Iterator<Item = NodeMut> {
-        self.composition
-            .iter()
-            .map(|binding| (binding, self.get_mut(binding.clone()).unwrap()))
-            .into_iter()
-    }
+        let id = self.next_id();
+        self.params.push((name, id));
 
-    pub fn first(&self) -> Option<NodeRef> {
-        self.composition
-            .first()
-            .map(|binding| (binding, self.get(binding.clone()).unwrap()))
+        id
     }
 
-    pub fn first_mut(&self) -> Option<NodeMut> {
-        self.composition
-            .first()
-            .map(|binding| (binding, self.get_mut(binding.clone()).unwrap()))
-    }
-
-    pub fn last(&self) -> Option<NodeRef> {
-        self.composition
-            .last()
-            .map(|binding| (binding, self.get(binding.clone()).unwrap()))
-    }
+    pub(super) fn push(&mut self, kind: InstructionKind) -> ValueId {
+        if let Some(id) = self.numbering.get(&kind) {
+            return *id;
+        }
 
-    pub fn last_mut(&self) -> Option<NodeMut> {
-        self.composition
-            .last()
-            .map(|binding| (binding, self.get_mut(binding.clone()).unwrap()))
-    }
+        let result = self.next_id();
+        self.numbering.insert(kind, result);
+        self.instructions.push(Instruction { result, kind });
 
-    pub fn retain(&mut self, f: impl Fn(&Binding) -> bool) {
-        self.cache.retain(|binding, _| f(binding));
-        self.composition
-            .retain(|binding| self.cache.contains_key(binding));
+        result
     }
-}
 
-impl core::fmt::Debug for Nodes {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_list()
-            .entries(
-                self.composition
-                    .iter()
-                    .map(|binding| self.cache.get(binding).unwrap().borrow().clone()),
-            )
-            .finish()
+    pub(super) fn finish(self, result: ValueId) -> Function {
+        Function {
+            params: self.params,
+            instructions: self.instructions,
+            result,
+        }
     }
 }
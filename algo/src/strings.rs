@@ -1,26 +1,120 @@
 pub use intaglio::Symbol;
 
 use intaglio::SymbolTable;
-use parking_lot::RwLock;
+use parking_lot::{MappedRwLockReadGuard, RwLock, RwLockReadGuard};
 
 lazy_static::lazy_static! {
-    static ref STRING_CACHE: RwLock<SymbolTable> = RwLock::new(SymbolTable::new());
+    static ref STRING_CACHE: RwLock<Interner> = RwLock::new(Interner::default());
 }
 
+/// Maximum number of unique symbols the string cache will hold, matching `intaglio`'s `u32` ids.
+const SYMBOL_LIMIT: usize = u32::MAX as usize;
+
+/// A table of interned strings, for compiling without touching the shared string cache.
+///
+/// Symbols are only meaningful to the interner that created them. `Display` impls and reports
+/// resolve symbols through the shared cache, so symbols from a separate interner should be resolved
+/// with [`Interner::resolve`] instead.
+#[derive(Debug, Default)]
+pub struct Interner(SymbolTable);
+
+impl Interner {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Interns `string`, returning `None` if the interner is exhausted.
+    pub fn intern(&mut self, string: &str) -> Option<Symbol> {
+        intern_bounded(&mut self.0, string, SYMBOL_LIMIT)
+    }
+
+    /// The symbol for `string`, if it has been interned.
+    #[inline]
+    pub fn get(&self, string: &str) -> Option<Symbol> {
+        self.0.check_interned(string)
+    }
+
+    /// The string `symbol` was interned from, or `None` if it is unknown to this interner.
+    #[inline]
+    pub fn resolve(&self, symbol: Symbol) -> Option<&str> {
+        self.0.get(symbol)
+    }
+}
+
+/// Runs `f` with the interner behind the shared string cache.
+///
+/// Other threads can't intern or resolve symbols until `f` returns, and `f` itself must not call
+/// functions that use the shared cache.
+pub(crate) fn with_shared_interner<T>(f: impl FnOnce(&mut Interner) -> T) -> T {
+    f(&mut STRING_CACHE.write())
+}
+
+/// Interns `string`, or returns its existing symbol if it was interned before.
+///
+/// # Panics
+///
+/// Panics if the string cache is exhausted. Use [`try_intern_str`] where input can add symbols.
 pub fn intern_str(string: &str) -> Symbol {
+    try_intern_str(string).expect("symbol overflow in string cache")
+}
+
+/// Interns `string`, returning `None` rather than panicking if the string cache is exhausted.
+pub fn try_intern_str(string: &str) -> Option<Symbol> {
     let cache = STRING_CACHE.upgradable_read();
-    cache.check_interned(string).unwrap_or_else(|| {
+    cache.get(string).or_else(|| {
         let mut cache = parking_lot::RwLockUpgradableReadGuard::upgrade(cache);
-        cache
-            .intern(string.to_string())
-            .expect("symbol overflow in string cache")
+        cache.intern(string)
     })
 }
 
+fn intern_bounded(table: &mut SymbolTable, string: &str, limit: usize) -> Option<Symbol> {
+    if let Some(symbol) = table.check_interned(string) {
+        Some(symbol)
+    } else if table.len() < limit {
+        table.intern(string.to_string()).ok()
+    } else {
+        None
+    }
+}
+
 pub fn get_intern_str(symbol: Symbol) -> String {
     let cache = STRING_CACHE.read();
     cache
-        .get(symbol)
+        .resolve(symbol)
         .expect("unknown symbol for symbol table")
         .to_string() // FIXME: Don't allocate a new string here
 }
+
+/// Resolves `symbol` back to the string it was interned from, or `None` if it is unknown.
+///
+/// The returned guard holds a read lock on the shared string cache, so other threads may resolve
+/// symbols concurrently, but interning a new string blocks until the guard is dropped. Don't hold
+/// it across a call that may intern.
+pub fn resolve(symbol: Symbol) -> Option<MappedRwLockReadGuard<'static, str>> {
+    RwLockReadGuard::try_map(STRING_CACHE.read(), |cache| cache.resolve(symbol)).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{intern_bounded, intern_str, resolve};
+    use intaglio::SymbolTable;
+
+    #[test]
+    fn bounded_overflow() {
+        let mut table = SymbolTable::new();
+
+        let a = intern_bounded(&mut table, "a", 2).unwrap();
+        assert!(intern_bounded(&mut table, "b", 2).is_some());
+        assert_eq!(intern_bounded(&mut table, "c", 2), None);
+        // Already-interned strings still resolve once the table is full.
+        assert_eq!(intern_bounded(&mut table, "a", 2), Some(a));
+    }
+
+    #[test]
+    fn resolve_interned() {
+        let symbol = intern_str("resolve_interned");
+
+        assert_eq!(resolve(symbol).as_deref(), Some("resolve_interned"));
+    }
+}
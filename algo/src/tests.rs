@@ -1,4 +1,4 @@
-use crate::{lexer::TokenKind, Error};
+use crate::{lexer::TokenKind, Error, ErrorKind};
 use ariadne::Source;
 use chumsky::Parser;
 use std::fmt::Debug;
@@ -20,10 +20,317 @@ pub fn parse_and_eq<T: Debug + PartialEq>(
         Ok(t) => assert_eq!(&t, other_t),
         Err(errs) => {
             for err in errs {
-                err.generate_report().eprint(Source::from(input)).unwrap();
+                err.generate_report(input)
+                    .eprint(Source::from(input))
+                    .unwrap();
             }
 
             panic!()
         }
     }
 }
+
+pub fn render_report(input: &str, err: &Error) -> String {
+    let mut buf = Vec::new();
+    err.generate_report(input)
+        .write(Source::from(input), &mut buf)
+        .unwrap();
+
+    String::from_utf8(buf).unwrap()
+}
+
+#[test]
+fn separator_help() {
+    let input = "(a b)";
+    let errs = crate::parser::parse(crate::lexer::lex(input)).unwrap_err();
+
+    assert_eq!(errs.len(), 1);
+    assert!(render_report(input, &errs[0]).contains("did you forget a separator?"));
+}
+
+#[test]
+fn separator_help_absent() {
+    let input = "(a +)";
+    let errs = crate::parser::parse(crate::lexer::lex(input)).unwrap_err();
+
+    assert!(errs
+        .iter()
+        .all(|err| !render_report(input, err).contains("did you forget a separator?")));
+}
+
+#[test]
+fn report_non_ascii_source() {
+    let input = "var naïve: () => ünknown;";
+    // Starts inside the two-byte `ü`, which ariadne would otherwise be handed mid-character.
+    let err = Error::general(19..26, "split character", None);
+    let report = render_report(input, &err);
+
+    assert!(report.contains("split character"));
+    assert!(report.contains("ünknown"));
+}
+
+#[test]
+fn label_start() {
+    let input = "1 => a\nvar b: () => ;";
+    let errs = crate::parser::parse(crate::lexer::lex(input)).unwrap_err();
+
+    assert_eq!(errs.len(), 1);
+    assert_eq!(errs[0].label(), Some("parse_vardef"));
+    assert_eq!(errs[0].label_span().map(|span| span.start), Some(7));
+    assert!(render_report(input, &errs[0]).contains("while parsing parse_vardef (starting here)"));
+}
+
+#[test]
+fn merge_expected() {
+    use chumsky::primitive::just;
+
+    let input = "*";
+    let errs = just(TokenKind::Add)
+        .or(just(TokenKind::Sub))
+        .parse(crate::lexer::lex(input))
+        .unwrap_err();
+
+    assert_eq!(errs.len(), 1);
+    assert!(render_report(input, &errs[0]).contains("expected one of '+', '-'"));
+}
+
+#[test]
+fn merge_prefers_longer_span() {
+    use chumsky::Error as _;
+
+    let short = Error::general(0..1, "short", None);
+    let long = Error::general(0..4, "long", None);
+
+    assert_eq!(short.merge(long).span(), &(0..4));
+}
+
+#[test]
+fn merge_disjoint_spans() {
+    use chumsky::Error as _;
+
+    let first = Error::unexpected(4..6, vec![TokenKind::Add], Some(TokenKind::Flow), None);
+    let second = Error::unexpected(0..1, vec![TokenKind::Sub], None, None);
+    let merged = first.merge(second);
+
+    assert_eq!(merged.span(), &(0..6));
+    assert!(matches!(
+        merged.kind(),
+        ErrorKind::Unexpected { expected, .. } if expected == &[TokenKind::Add, TokenKind::Sub]
+    ));
+}
+
+#[test]
+fn operator_precedence() {
+    use crate::Operator::*;
+
+    assert!(Exp.precedence() > Mul.precedence());
+    assert!(Mul.precedence() > Add.precedence());
+    assert_eq!(Mul.precedence(), Rem.precedence());
+    assert!(Add.precedence() > Shl.precedence());
+    assert!(BitAnd.precedence() > BitXor.precedence());
+    assert!(BitXor.precedence() > BitOr.precedence());
+    assert!(BitOr.precedence() > Less.precedence());
+    assert!(Less.precedence() > And.precedence());
+    assert!(And.precedence() > Or.precedence());
+    assert!(Or.precedence() > Assign.precedence());
+}
+
+#[test]
+fn operator_associativity() {
+    use crate::{Associativity, Operator::*};
+
+    assert_eq!(Exp.associativity(), Associativity::Right);
+    assert_eq!(Assign.associativity(), Associativity::Right);
+    assert_eq!(Add.associativity(), Associativity::Left);
+    assert_eq!(Eq.associativity(), Associativity::Left);
+}
+
+#[test]
+fn operator_from_token() {
+    use crate::Operator;
+
+    let cases = [
+        ("**", Operator::Exp),
+        ("+", Operator::Add),
+        ("-", Operator::Sub),
+        ("*", Operator::Mul),
+        ("/", Operator::Div),
+        ("%", Operator::Rem),
+        (">>", Operator::Shr),
+        ("<<", Operator::Shl),
+        ("^", Operator::BitXor),
+        ("&", Operator::BitAnd),
+        ("|", Operator::BitOr),
+        ("=", Operator::Eq),
+        ("!=", Operator::NotEq),
+        (">", Operator::Greater),
+        (">=", Operator::GreaterEq),
+        ("<", Operator::Less),
+        ("<=", Operator::LessEq),
+        ("||", Operator::Or),
+        ("^^", Operator::Xor),
+        ("&&", Operator::And),
+        ("?>", Operator::Clow),
+        ("??", Operator::Cerm),
+        (":", Operator::Assign),
+    ];
+
+    for (input, op) in cases {
+        let (token, _) = crate::lexer::lex(input).next().unwrap();
+        assert_eq!(Operator::try_from(&token), Ok(op), "converting {input:?}");
+    }
+}
+
+#[test]
+fn operator_from_non_operator() {
+    use crate::Operator;
+
+    assert_eq!(Operator::try_from(&TokenKind::Flow), Err(TokenKind::Flow));
+    assert_eq!(
+        Operator::try_from(&TokenKind::GroupOpen),
+        Err(TokenKind::GroupOpen)
+    );
+}
+
+#[test]
+fn error_codes() {
+    use crate::types::Type;
+
+    let cases = [
+        (Error::no_top_level_expr(), "E0001"),
+        (Error::undeclared_var(0..1, "a", None), "E0002"),
+        (Error::unexpected(0..1, vec![], None, None), "E0003"),
+        (
+            <Error as chumsky::Error<TokenKind>>::unclosed_delimiter(
+                0..1,
+                TokenKind::GroupOpen,
+                1..2,
+                TokenKind::GroupClose,
+                None,
+            ),
+            "E0004",
+        ),
+        (
+            Error::type_mismatch(0..1, Type::INT, Type::Bool, None),
+            "E0005",
+        ),
+        (Error::general(0..1, "general", None), "E0007"),
+    ];
+
+    for (err, code) in cases {
+        assert_eq!(err.kind().code(), code, "code of {:?}", err.kind());
+    }
+}
+
+#[test]
+fn error_code_in_report() {
+    let err = Error::general(0..1, "general", None);
+
+    assert!(render_report("a", &err).contains("[E0007]"));
+}
+
+#[test]
+fn report_without_color() {
+    use crate::ReportConfig;
+
+    let input = "(a b";
+    let config = ReportConfig { color: false };
+    let errs = [
+        Error::unexpected(
+            3..4,
+            vec![TokenKind::Separator],
+            Some(TokenKind::Symbol(crate::strings::intern_str("b"))),
+            Some("parse_tuple"),
+        ),
+        <Error as chumsky::Error<TokenKind>>::unclosed_delimiter(
+            0..1,
+            TokenKind::GroupOpen,
+            4..4,
+            TokenKind::GroupClose,
+            None,
+        ),
+        Error::type_mismatch(
+            1..2,
+            crate::types::Type::INT,
+            crate::types::Type::Bool,
+            None,
+        ),
+    ];
+
+    for err in errs {
+        let mut buf = Vec::new();
+        err.generate_report_with(input, config)
+            .write(Source::from(input), &mut buf)
+            .unwrap();
+
+        assert!(!String::from_utf8(buf).unwrap().contains("\x1b["));
+    }
+}
+
+#[test]
+fn unclosed_delimiter_labels() {
+    let input = "(1, 2;";
+    let errs = crate::parser::parse(crate::lexer::lex(input)).unwrap_err();
+    let report = render_report(input, &errs[0]);
+
+    assert!(matches!(errs[0].kind(), ErrorKind::UnclosedDelimiter { .. }));
+    assert!(report.contains("unclosed '(' opened here"));
+    assert!(report.contains("expected ')' here"));
+    assert_eq!(report.matches("unclosed delimiter").count(), 1);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serialize_unexpected() {
+    let err = Error::unexpected(
+        2..3,
+        vec![TokenKind::Flow, TokenKind::Terminator],
+        Some(TokenKind::Add),
+        None,
+    );
+    let json = serde_json::to_string(&err).unwrap();
+
+    assert!(json.contains(r#""span":{"start":2,"end":3}"#));
+    assert!(json.contains(r#""expected":["=>",";"]"#));
+    assert!(json.contains(r#""found":"+""#));
+}
+
+#[test]
+fn separate_interners() {
+    use crate::strings::Interner;
+
+    let compile = |input: &'static str| {
+        std::thread::spawn(move || {
+            let mut interner = Interner::new();
+            let tokens = crate::lexer::lex_with(input, &mut interner);
+            let names = tokens
+                .clone()
+                .filter_map(|(kind, _)| match kind {
+                    TokenKind::Symbol(symbol) => Some(symbol),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+
+            assert!(crate::parser::parse(tokens).is_ok());
+            (names, interner)
+        })
+    };
+
+    let (a, b) = (compile("alpha + gamma"), compile("beta + gamma"));
+    let (a_names, a_interner) = a.join().unwrap();
+    let (b_names, b_interner) = b.join().unwrap();
+
+    let resolve = |names: &[_], interner: &Interner| {
+        names
+            .iter()
+            .map(|name| interner.resolve(*name).unwrap().to_owned())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(resolve(&a_names, &a_interner), ["alpha", "gamma"]);
+    assert_eq!(resolve(&b_names, &b_interner), ["beta", "gamma"]);
+
+    // Each interner numbers its own symbols, and never sees the other's.
+    assert_eq!(a_names, b_names);
+    assert_eq!(a_interner.get("beta"), None);
+    assert_eq!(b_interner.get("alpha"), None);
+}
@@ -1,50 +1,359 @@
-use std::collections::BTreeMap;
-
 ///! Module defining everything related to the Algo type system.
 use crate::{
-    interned,
     parser::{Expression, ExpressionKind},
-    strings::Symbol,
+    strings::{get_intern_str, Symbol},
     Error, Operator,
 };
 
-// #[derive(Debug, Clone, PartialEq, Eq)]
-// pub enum Type {
-//     Unit, // is `()`
-//     Int,  // is `isize`
-//     UInt, // is `usize`
-//     Bool, // is `bool`
+/// Width of an integer type. `Ptr` is the default for `Int`/`UInt`, i.e. `isize`/`usize`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IntWidth {
+    W8,
+    W16,
+    W32,
+    W64,
+    Ptr,
+}
 
-//     Tuple(Vec<(Symbol, Self)>),
-//     Array { ty: Box<Self>, len: Option<usize> },
+impl IntWidth {
+    /// The width in bits, or `None` for pointer-width.
+    pub const fn bits(self) -> Option<u32> {
+        match self {
+            Self::W8 => Some(8),
+            Self::W16 => Some(16),
+            Self::W32 => Some(32),
+            Self::W64 => Some(64),
+            Self::Ptr => None,
+        }
+    }
+}
 
-//     Checked(Symbol),
-// }
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+    Unit,           // is `()`
+    Int(IntWidth),  // is `isize`, or `i8`..`i64`
+    UInt(IntWidth), // is `usize`, or `u8`..`u64`
+    /// An `f64`. Integer values never widen to it implicitly, so `5` in a `Float` context is a
+    /// type mismatch and must be written as `5.0`.
+    Float,
+    Bool, // is `bool`
+    /// Text, comparable with `=`/`!=`. There is no concatenation, so arithmetic on it is an error.
+    Str,
+    Char, // is `char`
 
-static TYPES_TMP: Types = Types::new();
+    /// Elements with an optional field name; anonymous tuple values have no names.
+    Tuple(Vec<(Option<Symbol>, Self)>),
+    Array {
+        ty: Box<Self>,
+        len: Option<usize>,
+    },
+    Expression {
+        input: Box<Self>,
+        output: Box<Self>,
+    },
 
-pub struct Type {
-    ops: Box<[Operator]>,
+    Checked(Symbol),
 }
 
-pub struct Types(BTreeMap<Symbol, Type>);
+impl Type {
+    /// The pointer-width signed integer, written `Int`.
+    pub const INT: Type = Type::Int(IntWidth::Ptr);
+    /// The pointer-width unsigned integer, written `UInt`.
+    pub const UINT: Type = Type::UInt(IntWidth::Ptr);
+
+    /// The size in bytes of a fixed-width integer type, or `None` for any other type (including
+    /// pointer-width integers).
+    pub fn size_bytes(&self) -> Option<usize> {
+        match self {
+            Type::Int(width) | Type::UInt(width) => width.bits().map(|bits| bits as usize / 8),
+            _ => None,
+        }
+    }
+
+    /// Builds the type of an expression taking `input` to `output`.
+    pub fn func(input: Type, output: Type) -> Self {
+        Self::Expression {
+            input: Box::new(input),
+            output: Box::new(output),
+        }
+    }
+
+    /// Builds the curried chain `inputs[0] -> inputs[1] -> ... -> output`.
+    ///
+    /// With no inputs, this is an expression from `()` to `output`.
+    pub fn func_n(inputs: &[Type], output: Type) -> Self {
+        match inputs.split_last() {
+            Some((last, rest)) => rest
+                .iter()
+                .rev()
+                .fold(Self::func(last.clone(), output), |output, input| {
+                    Self::func(input.clone(), output)
+                }),
+            None => Self::func(Self::Unit, output),
+        }
+    }
+
+    /// Rewrites the type into its canonical form, so that equivalent spellings compare equal.
+    ///
+    /// Unnamed single-element tuples are transparent, so `((Int))` becomes `Int`. A named
+    /// single-element tuple such as `(x: Int)` is kept, since the name is meaningful.
+    #[must_use]
+    pub fn canonicalize(self) -> Self {
+        match self {
+            Type::Tuple(mut fields) if fields.len() == 1 && fields[0].0.is_none() => {
+                fields.remove(0).1.canonicalize()
+            }
+
+            Type::Tuple(fields) => Type::Tuple(
+                fields
+                    .into_iter()
+                    .map(|(name, ty)| (name, ty.canonicalize()))
+                    .collect(),
+            ),
+
+            Type::Array { ty, len } => Type::Array {
+                ty: Box::new(ty.canonicalize()),
+                len,
+            },
 
-impl Types {
-    const fn new() -> Self {
-        Self(BTreeMap::new())
+            Type::Expression { input, output } => {
+                Type::func(input.canonicalize(), output.canonicalize())
+            }
+
+            ty => ty,
+        }
+    }
+
+    /// Finds the most specific type compatible with both `self` and `other`, or `None` if they
+    /// genuinely conflict.
+    ///
+    /// An array without a length unifies with one that has a length, taking the concrete length,
+    /// and an unnamed tuple field unifies with a named one, taking the name.
+    pub fn unify(&self, other: &Type) -> Option<Type> {
+        match (self, other) {
+            (Type::Tuple(fields), Type::Tuple(other_fields))
+                if fields.len() == other_fields.len() =>
+            {
+                fields
+                    .iter()
+                    .zip(other_fields.iter())
+                    .map(|((name, ty), (other_name, other_ty))| {
+                        Some((unify_known(*name, *other_name)?, ty.unify(other_ty)?))
+                    })
+                    .collect::<Option<Vec<_>>>()
+                    .map(Type::Tuple)
+            }
+
+            (
+                Type::Array { ty, len },
+                Type::Array {
+                    ty: other_ty,
+                    len: other_len,
+                },
+            ) => Some(Type::Array {
+                ty: Box::new(ty.unify(other_ty)?),
+                len: unify_known(*len, *other_len)?,
+            }),
+
+            (
+                Type::Expression { input, output },
+                Type::Expression {
+                    input: other_input,
+                    output: other_output,
+                },
+            ) => Some(Type::func(
+                input.unify(other_input)?,
+                output.unify(other_output)?,
+            )),
+
+            (ty, other_ty) if ty == other_ty => Some(ty.clone()),
+            _ => None,
+        }
+    }
+
+    /// Pinpoints the nested component at which `found` diverges from `self`, producing a note
+    /// such as `element 1: expected Int, found Bool`.
+    ///
+    /// Returns `None` if the types are equal, or if they already differ at the top level (in which
+    /// case there is nothing more specific to point at).
+    pub fn diff_note(&self, found: &Type) -> Option<String> {
+        let mut path = Vec::new();
+        let note = diff_types(self, found, &mut path)?;
+
+        if path.is_empty() {
+            None
+        } else {
+            Some(format!("{}: {note}", path.join(" > ")))
+        }
+    }
+}
+
+/// Renders the type in the same syntax it's written in, e.g. `(xs: [Int, 3]) -> Bool`.
+impl core::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Unit => f.write_str("()"),
+            Type::Int(IntWidth::Ptr) => f.write_str("Int"),
+            Type::UInt(IntWidth::Ptr) => f.write_str("UInt"),
+            Type::Int(width) => write!(f, "i{}", width.bits().unwrap_or_default()),
+            Type::UInt(width) => write!(f, "u{}", width.bits().unwrap_or_default()),
+            Type::Float => f.write_str("Float"),
+            Type::Bool => f.write_str("Bool"),
+            Type::Str => f.write_str("Str"),
+            Type::Char => f.write_str("Char"),
+
+            Type::Tuple(fields) => {
+                f.write_str("(")?;
+                for (index, (name, ty)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+
+                    match name {
+                        Some(name) => write!(f, "{}: {ty}", get_intern_str(*name))?,
+                        None => write!(f, "{ty}")?,
+                    }
+                }
+                f.write_str(")")
+            }
+
+            Type::Array { ty, len: Some(len) } => write!(f, "[{ty}, {len}]"),
+            Type::Array { ty, len: None } => write!(f, "[{ty}]"),
+
+            // Arrows associate to the right, so only a nested input needs grouping.
+            Type::Expression { input, output } => match **input {
+                Type::Expression { .. } => write!(f, "({input}) -> {output}"),
+                _ => write!(f, "{input} -> {output}"),
+            },
+
+            Type::Checked(symbol) => f.write_str(&get_intern_str(*symbol)),
+        }
+    }
+}
+
+/// Serializes as the written type, e.g. `"[Int, 3]"`, since symbols only mean something in-process.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Type {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Unifies two optionally-known values: an unknown side takes the other's value, while two known
+/// values must agree. The outer `None` signals a conflict.
+#[allow(clippy::option_option)]
+fn unify_known<T: PartialEq>(a: Option<T>, b: Option<T>) -> Option<Option<T>> {
+    match (a, b) {
+        (Some(a), Some(b)) => (a == b).then_some(Some(a)),
+        (a, b) => Some(a.or(b)),
+    }
+}
+
+fn diff_types(expected: &Type, found: &Type, path: &mut Vec<String>) -> Option<String> {
+    match (expected, found) {
+        (Type::Tuple(expected), Type::Tuple(found)) if expected.len() == found.len() => {
+            expected.iter().zip(found.iter()).enumerate().find_map(
+                |(index, ((expected_name, expected), (found_name, found)))| {
+                    path.push(format!("element {index}"));
+
+                    let note = match (expected_name, found_name) {
+                        (Some(expected_name), Some(found_name)) if expected_name != found_name => {
+                            Some(format!(
+                                "expected field `{}`, found `{}`",
+                                get_intern_str(*expected_name),
+                                get_intern_str(*found_name)
+                            ))
+                        }
+                        _ => diff_types(expected, found, path),
+                    };
+
+                    if note.is_none() {
+                        path.pop();
+                    }
+
+                    note
+                },
+            )
+        }
+
+        (Type::Tuple(expected), Type::Tuple(found)) => Some(format!(
+            "expected {} elements, found {}",
+            expected.len(),
+            found.len()
+        )),
+
+        (
+            Type::Array {
+                ty: expected_ty,
+                len: expected_len,
+            },
+            Type::Array {
+                ty: found_ty,
+                len: found_len,
+            },
+        ) => {
+            path.push("array element".to_owned());
+            if let Some(note) = diff_types(expected_ty, found_ty, path) {
+                return Some(note);
+            }
+            path.pop();
+
+            (expected_len != found_len).then(|| {
+                path.push("array length".to_owned());
+
+                let fmt_len = |len: &Option<usize>| {
+                    len.map_or("unspecified".to_owned(), |len| len.to_string())
+                };
+                format!(
+                    "expected {}, found {}",
+                    fmt_len(expected_len),
+                    fmt_len(found_len)
+                )
+            })
+        }
+
+        (
+            Type::Expression {
+                input: expected_input,
+                output: expected_output,
+            },
+            Type::Expression {
+                input: found_input,
+                output: found_output,
+            },
+        ) => [
+            ("input", expected_input, found_input),
+            ("output", expected_output, found_output),
+        ]
+        .into_iter()
+        .find_map(|(segment, expected, found)| {
+            path.push(segment.to_owned());
+            let note = diff_types(expected, found, path);
+            if note.is_none() {
+                path.pop();
+            }
+
+            note
+        }),
+
+        (expected, found) if expected == found => None,
+        (expected, found) => Some(format!("expected {expected}, found {found}")),
     }
 }
 
 #[derive(Debug)]
 pub struct TypedExpression {
-    ty_id: Symbol,
+    ty: Type,
     expr: Expression,
 }
 
-pub fn type_exprs(
-    types: &Types,
-    exprs: Vec<Expression>,
-) -> Result<Vec<TypedExpression>, Vec<Error>> {
+/// Types each top-level expression.
+///
+/// # Errors
+///
+/// Returns the error for every expression that fails to type, if any do. Identifiers and
+/// definitions always fail, as there is no name resolution to type them with yet.
+pub fn type_exprs(exprs: Vec<Expression>) -> Result<Vec<TypedExpression>, Vec<Error>> {
     let (exprs, errs) =
         exprs
             .into_iter()
@@ -64,31 +373,735 @@ pub fn type_exprs(
     }
 }
 
-#[allow(unused_variables)]
-fn type_expr(types: &Types, expr: &Expression) -> Result<Type, Error> {
+fn type_expr(expr: &Expression) -> Result<Type, Error> {
     match expr.kind() {
-        ExpressionKind::Error => todo!(),
+        ExpressionKind::Error => Err(Error::general(
+            expr.span().clone(),
+            "cannot type an expression that failed to parse",
+            Some("type_expr.error"),
+        )),
+
+        ExpressionKind::Unit => Ok(Type::Unit),
+        ExpressionKind::Int(_) => Ok(Type::INT),
+        ExpressionKind::UInt(_) => Ok(Type::UINT),
+        ExpressionKind::Float(_) => Ok(Type::Float),
+        ExpressionKind::Bool(_) => Ok(Type::Bool),
+        ExpressionKind::Str(_) => Ok(Type::Str),
+        ExpressionKind::Char(_) => Ok(Type::Char),
+
+        ExpressionKind::Binary { lhs, op, rhs } => type_binary(expr, lhs, *op, rhs),
+
+        // Each expression in a sequence is evaluated in turn, and the last gives the value.
+        ExpressionKind::Compound(exprs) => {
+            exprs.iter().try_fold(Type::Unit, |_, expr| type_expr(expr))
+        }
+
+        ExpressionKind::ControlFlow { from, into } => type_expr(from)
+            .and_then(|from_ty| into.as_ref().map_or(Ok(from_ty), |into| type_expr(into))),
+
+        ExpressionKind::Tuple(exprs) => exprs
+            .iter()
+            .map(|expr| match expr.kind() {
+                // Named fields are written as assignments, e.g. `(a: 1, b: false)`.
+                ExpressionKind::Binary {
+                    lhs,
+                    op: Operator::Assign,
+                    rhs,
+                } => match lhs.kind() {
+                    ExpressionKind::Identifier(name) => Ok((Some(*name), type_expr(rhs)?)),
+                    _ => Ok((None, type_expr(expr)?)),
+                },
+                _ => Ok((None, type_expr(expr)?)),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Type::Tuple),
+
+        ExpressionKind::If {
+            cond,
+            then,
+            otherwise,
+        } => type_if(cond, then, otherwise.as_deref()),
+
+        ExpressionKind::TupleIndex { tuple, index } => match type_expr(tuple)? {
+            Type::Tuple(fields) => fields.get(*index).map(|(_, ty)| ty.clone()).ok_or_else(|| {
+                Error::general(
+                    expr.span().clone(),
+                    &format!(
+                        "tuple index {index} is out of range for a tuple of {} elements",
+                        fields.len()
+                    ),
+                    Some("type_expr.tuple_index"),
+                )
+            }),
+            ty => Err(Error::general(
+                tuple.span().clone(),
+                &format!("cannot index into `{ty}`, which is not a tuple"),
+                Some("type_expr.tuple_index"),
+            )),
+        },
+
+        ExpressionKind::TupleField { tuple, field } => type_tuple_field(expr, tuple, *field),
+        ExpressionKind::ArrayIndex { array, index } => type_array_index(array, index),
+        ExpressionKind::Array(elements) => type_array(expr, elements),
+
+        // Typing names needs scopes to resolve them in, which don't exist yet.
+        ExpressionKind::TypeId(name)
+        | ExpressionKind::Identifier(name)
+        | ExpressionKind::VarDef { name, .. }
+        | ExpressionKind::TypeDef { name, .. } => Err(Error::general(
+            expr.span().clone(),
+            &format!(
+                "cannot type `{}`, as name resolution isn't implemented yet",
+                get_intern_str(*name)
+            ),
+            Some("type_expr.name"),
+        )),
+    }
+}
+
+/// Types an array literal, whose elements must all unify to a single element type.
+fn type_array(expr: &Expression, elements: &[Expression]) -> Result<Type, Error> {
+    let (first, rest) = elements.split_first().ok_or_else(|| {
+        Error::general(
+            expr.span().clone(),
+            "cannot infer the element type of an empty array",
+            Some("type_expr.array"),
+        )
+    })?;
+
+    rest.iter()
+        .try_fold(type_expr(first)?, |ty, element| {
+            let element_ty = type_expr(element)?;
+            ty.unify(&element_ty).ok_or_else(|| {
+                Error::type_mismatch(
+                    element.span().clone(),
+                    ty,
+                    element_ty,
+                    Some("type_expr.array"),
+                )
+            })
+        })
+        .map(|ty| Type::Array {
+            ty: Box::new(ty),
+            len: Some(elements.len()),
+        })
+}
+
+/// Types `array[index]`, which requires an array indexed by any integer type.
+fn type_array_index(array: &Expression, index: &Expression) -> Result<Type, Error> {
+    let array_ty = type_expr(array)?;
+    let Type::Array { ty, .. } = array_ty else {
+        return Err(Error::general(
+            array.span().clone(),
+            &format!("cannot index into `{array_ty}`, which is not an array"),
+            Some("type_expr.array_index"),
+        ));
+    };
+
+    match type_expr(index)? {
+        Type::Int(_) | Type::UInt(_) => Ok(*ty),
+        index_ty => Err(Error::type_mismatch(
+            index.span().clone(),
+            Type::UINT,
+            index_ty,
+            Some("type_expr.array_index"),
+        )),
+    }
+}
+
+/// Types `tuple.field`, looking the field up by name.
+fn type_tuple_field(expr: &Expression, tuple: &Expression, field: Symbol) -> Result<Type, Error> {
+    match type_expr(tuple)? {
+        Type::Tuple(fields) => fields
+            .iter()
+            .find(|(name, _)| *name == Some(field))
+            .map(|(_, ty)| ty.clone())
+            .ok_or_else(|| {
+                Error::general(
+                    expr.span().clone(),
+                    &format!(
+                        "no field `{}` on `{}`",
+                        get_intern_str(field),
+                        Type::Tuple(fields.clone())
+                    ),
+                    Some("type_expr.tuple_field"),
+                )
+            }),
+        ty => Err(Error::general(
+            tuple.span().clone(),
+            &format!("cannot access a field of `{ty}`, which is not a tuple"),
+            Some("type_expr.tuple_field"),
+        )),
+    }
+}
+
+/// Types a binary operation. Arithmetic and comparisons need operands of the same type, and the
+/// logical operators need `Bool` on both sides.
+fn type_binary(
+    expr: &Expression,
+    lhs: &Expression,
+    op: Operator,
+    rhs: &Expression,
+) -> Result<Type, Error> {
+    let lhs_ty = type_expr(lhs)?;
+    let rhs_ty = type_expr(rhs)?;
+    let same_ty = |ty: Type| {
+        if lhs_ty == rhs_ty {
+            Ok(ty)
+        } else {
+            Err(Error::type_mismatch(
+                rhs.span().clone(),
+                lhs_ty.clone(),
+                rhs_ty.clone(),
+                Some("type_expr.binary"),
+            ))
+        }
+    };
+
+    match (&lhs_ty, op) {
+        (
+            Type::Float,
+            Operator::Shl | Operator::Shr | Operator::BitAnd | Operator::BitOr | Operator::BitXor,
+        ) => Err(Error::general(
+            expr.span().clone(),
+            "bitwise operators are not supported on `Float`",
+            Some("type_expr.binary"),
+        )),
+
+        (Type::Int(_) | Type::UInt(_) | Type::Float, op) if op.is_arithmetic() => {
+            same_ty(lhs_ty.clone())
+        }
+
+        (_, op) if op.is_arithmetic() => Err(Error::general(
+            expr.span().clone(),
+            &format!("arithmetic is not supported on `{lhs_ty}`"),
+            Some("type_expr.binary"),
+        )),
+
+        // Any two values of the same type compare for equality, but only numbers are ordered.
+        (_, Operator::Eq | Operator::NotEq) => same_ty(Type::Bool),
+        (Type::Int(_) | Type::UInt(_) | Type::Float, op) if op.is_boolean() => same_ty(Type::Bool),
+        (_, op) if op.is_boolean() => Err(Error::general(
+            expr.span().clone(),
+            &format!("ordering is not supported on `{lhs_ty}`"),
+            Some("type_expr.binary"),
+        )),
+
+        (Type::Bool, Operator::And | Operator::Or | Operator::Xor) => same_ty(Type::Bool),
+        (_, Operator::And | Operator::Or | Operator::Xor) => Err(Error::type_mismatch(
+            lhs.span().clone(),
+            Type::Bool,
+            lhs_ty.clone(),
+            Some("type_expr.binary"),
+        )),
+
+        _ => Err(Error::general(
+            expr.span().clone(),
+            &format!("this operator is not supported on `{lhs_ty}`"),
+            Some("type_expr.binary"),
+        )),
+    }
+}
+
+/// Types an `if`, whose condition must be `Bool` and whose branches must unify.
+fn type_if(
+    cond: &Expression,
+    then: &Expression,
+    otherwise: Option<&Expression>,
+) -> Result<Type, Error> {
+    let cond_ty = type_expr(cond)?;
+    if cond_ty != Type::Bool {
+        return Err(Error::type_mismatch(
+            cond.span().clone(),
+            Type::Bool,
+            cond_ty,
+            Some("type_expr.if"),
+        ));
+    }
+
+    let then_ty = type_expr(then)?;
+    match otherwise {
+        Some(otherwise) => {
+            let otherwise_ty = type_expr(otherwise)?;
+            then_ty.unify(&otherwise_ty).ok_or_else(|| {
+                Error::type_mismatch(
+                    otherwise.span().clone(),
+                    then_ty,
+                    otherwise_ty,
+                    Some("type_expr.if"),
+                )
+            })
+        }
+
+        // Without an `else`, the missing branch is `()`, so `then` must be too.
+        None if then_ty == Type::Unit => Ok(Type::Unit),
+        None => Err(Error::type_mismatch(
+            then.span().clone(),
+            Type::Unit,
+            then_ty,
+            Some("type_expr.if"),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{type_exprs, IntWidth, Type};
+    use crate::{interned, ErrorKind};
 
-        ExpressionKind::Unit => Ok(types[&interned!("Unit")]),
-        ExpressionKind::Int(_) => Ok(types[&interned!("Int")]),
-        ExpressionKind::UInt(_) => Ok(types[&interned!("UInt")]),
-        ExpressionKind::Bool(_) => Ok(types[&interned!("Bool")]),
+    fn type_source(input: &str) -> Result<Vec<Type>, Vec<crate::Error>> {
+        let program = crate::parser::parse(crate::lexer::lex(input)).unwrap();
+        type_exprs(program.into_items())
+            .map(|exprs| exprs.into_iter().map(|expr| expr.ty).collect())
+    }
+
+    #[test]
+    fn func_n_chain() {
+        assert_eq!(
+            Type::func_n(&[Type::INT, Type::Bool], Type::UINT),
+            Type::func(Type::INT, Type::func(Type::Bool, Type::UINT))
+        );
+    }
+
+    #[test]
+    fn func_n_empty() {
+        assert_eq!(
+            Type::func_n(&[], Type::INT),
+            Type::func(Type::Unit, Type::INT)
+        );
+    }
+
+    #[test]
+    fn diff_expression_output() {
+        let expected = Type::func_n(&[Type::INT, Type::INT], Type::INT);
+        let found = Type::func_n(&[Type::INT, Type::INT], Type::Bool);
+
+        assert_eq!(
+            expected.diff_note(&found).as_deref(),
+            Some("output > output: expected Int, found Bool")
+        );
+    }
+
+    #[test]
+    fn diff_equal() {
+        assert_eq!(Type::INT.diff_note(&Type::INT), None);
+    }
+
+    #[test]
+    fn diff_top_level() {
+        assert_eq!(Type::INT.diff_note(&Type::Bool), None);
+    }
+
+    #[test]
+    fn diff_tuple_element() {
+        let expected = Type::Tuple(vec![
+            (Some(interned!("a")), Type::INT),
+            (Some(interned!("b")), Type::INT),
+        ]);
+        let found = Type::Tuple(vec![
+            (Some(interned!("a")), Type::INT),
+            (Some(interned!("b")), Type::Bool),
+        ]);
 
-        ExpressionKind::Binary { lhs, op, rhs } => {
-            let lhs_ty = type_expr(&*lhs)?;
-            let rhs_ty = type_expr(&*rhs)?;
+        assert_eq!(
+            expected.diff_note(&found).as_deref(),
+            Some("element 1: expected Int, found Bool")
+        );
+    }
+
+    #[test]
+    fn diff_nested_array() {
+        let array = |ty, len| Type::Array {
+            ty: Box::new(ty),
+            len,
+        };
+        let expected = Type::Tuple(vec![(
+            Some(interned!("xs")),
+            array(array(Type::UINT, Some(2)), None),
+        )]);
+        let found = Type::Tuple(vec![(
+            Some(interned!("xs")),
+            array(array(Type::UINT, Some(3)), None),
+        )]);
+
+        assert_eq!(
+            expected.diff_note(&found).as_deref(),
+            Some("element 0 > array element > array length: expected 2, found 3")
+        );
+    }
+
+    #[test]
+    fn float_arithmetic() {
+        assert_eq!(type_source("1.5 * 2e3 + 0.5").unwrap(), vec![Type::Float]);
+    }
+
+    #[test]
+    fn int_in_float_context() {
+        let errs = type_source("1.5 + 5").unwrap_err();
+
+        assert_eq!(errs.len(), 1);
+        assert!(matches!(
+            errs[0].kind(),
+            ErrorKind::TypeMismatch {
+                expected: Type::Float,
+                found: Type::INT,
+            }
+        ));
+    }
+
+    #[test]
+    fn float_bitwise() {
+        for input in [
+            "1.5 << 2.0",
+            "1.5 >> 2.0",
+            "1.5 & 2.0",
+            "1.5 | 2.0",
+            "1.5 ^ 2.0",
+        ] {
+            let errs = type_source(input).unwrap_err();
+
+            assert_eq!(errs.len(), 1, "errors for {input:?}");
+            assert!(
+                matches!(errs[0].kind(), ErrorKind::General(msg) if msg.contains("bitwise")),
+                "error for {input:?}: {:?}",
+                errs[0].kind()
+            );
         }
+    }
 
-        ExpressionKind::Compound(exprs) => todo!(),
+    #[test]
+    fn comparisons() {
+        for input in ["1 < 2", "1 = 1", "1.5 >= 2.5", "'a' != 'b'", "true = false"] {
+            assert_eq!(type_source(input).unwrap(), vec![Type::Bool], "{input:?}");
+        }
+    }
+
+    #[test]
+    fn comparison_mismatch() {
+        let errs = type_source("1 < true").unwrap_err();
+
+        assert!(matches!(
+            errs[0].kind(),
+            ErrorKind::TypeMismatch {
+                expected: Type::Int(_),
+                found: Type::Bool,
+            }
+        ));
+    }
+
+    #[test]
+    fn logic() {
+        for input in ["true && false", "true || false", "true ^^ false"] {
+            assert_eq!(type_source(input).unwrap(), vec![Type::Bool], "{input:?}");
+        }
+    }
 
-        ExpressionKind::ControlFlow { from, into } => type_expr(&*from)
-            .and_then(|from_ty| into.as_ref().map_or(Ok(from_ty), |into| type_expr(&*into))),
+    #[test]
+    fn logic_not_bool() {
+        for (input, found) in [("1 && true", Type::INT), ("true || 1", Type::INT)] {
+            let errs = type_source(input).unwrap_err();
 
-        ExpressionKind::Array(_) | ExpressionKind::Tuple(_) | ExpressionKind::Identifier(_) => {
-            todo!()
+            assert!(
+                matches!(
+                    errs[0].kind(),
+                    ErrorKind::TypeMismatch { expected: Type::Bool, found: ty } if *ty == found
+                ),
+                "error for {input:?}: {:?}",
+                errs[0].kind()
+            );
         }
+    }
+
+    #[test]
+    fn unsupported_operator() {
+        let errs = type_source("true + false").unwrap_err();
+
+        assert!(matches!(errs[0].kind(), ErrorKind::General(_)));
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Type::Unit.to_string(), "()");
+        assert_eq!(
+            Type::Array {
+                ty: Box::new(Type::Float),
+                len: Some(3),
+            }
+            .to_string(),
+            "[Float, 3]"
+        );
+        assert_eq!(
+            Type::Tuple(vec![
+                (
+                    Some(interned!("xs")),
+                    Type::Array {
+                        ty: Box::new(Type::Checked(interned!("Point"))),
+                        len: None,
+                    }
+                ),
+                (Some(interned!("ok")), Type::Bool),
+            ])
+            .to_string(),
+            "(xs: [Point], ok: Bool)"
+        );
+        assert_eq!(
+            Type::Tuple(vec![(None, Type::INT), (None, Type::Bool)]).to_string(),
+            "(Int, Bool)"
+        );
+    }
+
+    #[test]
+    fn display_expression() {
+        let curried = Type::func_n(&[Type::INT, Type::UINT], Type::Bool);
+        let higher_order = Type::func(Type::func(Type::INT, Type::INT), Type::Unit);
+
+        assert_eq!(curried.to_string(), "Int -> UInt -> Bool");
+        assert_eq!(higher_order.to_string(), "(Int -> Int) -> ()");
+    }
+
+    #[test]
+    fn unify_array_len() {
+        let array = |len| Type::Array {
+            ty: Box::new(Type::INT),
+            len,
+        };
+
+        assert_eq!(array(None).unify(&array(Some(3))), Some(array(Some(3))));
+        assert_eq!(array(Some(3)).unify(&array(None)), Some(array(Some(3))));
+        assert_eq!(array(Some(3)).unify(&array(Some(4))), None);
+    }
+
+    #[test]
+    fn unify_tuple_names() {
+        let named = Type::Tuple(vec![
+            (Some(interned!("a")), Type::INT),
+            (Some(interned!("b")), Type::Bool),
+        ]);
+        let anonymous = Type::Tuple(vec![(None, Type::INT), (Some(interned!("b")), Type::Bool)]);
+        let renamed = Type::Tuple(vec![
+            (Some(interned!("c")), Type::INT),
+            (Some(interned!("b")), Type::Bool),
+        ]);
+
+        assert_eq!(anonymous.unify(&named), Some(named.clone()));
+        assert_eq!(named.unify(&renamed), None);
+    }
+
+    #[test]
+    fn unify_mismatch() {
+        assert_eq!(Type::INT.unify(&Type::Bool), None);
+        assert_eq!(
+            Type::func(Type::INT, Type::INT).unify(&Type::func(Type::INT, Type::Bool)),
+            None
+        );
+    }
+
+    #[test]
+    fn canonicalize_nested_single() {
+        let single = |ty| Type::Tuple(vec![(None, ty)]);
+        let nested = single(single(single(single(Type::INT))));
+
+        assert_eq!(nested.canonicalize(), Type::INT);
+        assert_eq!(
+            Type::Array {
+                ty: Box::new(single(single(Type::Bool))),
+                len: Some(2),
+            }
+            .canonicalize(),
+            Type::Array {
+                ty: Box::new(Type::Bool),
+                len: Some(2),
+            }
+        );
+    }
+
+    #[test]
+    fn canonicalize_named_single() {
+        let named = Type::Tuple(vec![(
+            Some(interned!("x")),
+            Type::Tuple(vec![(None, Type::INT)]),
+        )]);
+
+        assert_eq!(
+            named.canonicalize(),
+            Type::Tuple(vec![(Some(interned!("x")), Type::INT)])
+        );
+    }
+
+    #[test]
+    fn size_bytes() {
+        assert_eq!(Type::Int(IntWidth::W8).size_bytes(), Some(1));
+        assert_eq!(Type::UInt(IntWidth::W16).size_bytes(), Some(2));
+        assert_eq!(Type::Int(IntWidth::W32).size_bytes(), Some(4));
+        assert_eq!(Type::UInt(IntWidth::W64).size_bytes(), Some(8));
+        assert_eq!(Type::INT.size_bytes(), None);
+        assert_eq!(Type::Bool.size_bytes(), None);
+    }
+
+    #[test]
+    fn display_fixed_width() {
+        assert_eq!(Type::Int(IntWidth::W32).to_string(), "i32");
+        assert_eq!(Type::UInt(IntWidth::W8).to_string(), "u8");
+        assert_eq!(Type::UINT.to_string(), "UInt");
+    }
+
+    #[test]
+    fn string_literal() {
+        assert_eq!(type_source(r#""abc""#).unwrap(), vec![Type::Str]);
+        assert_eq!(type_source("'a'").unwrap(), vec![Type::Char]);
+    }
+
+    #[test]
+    fn string_comparison() {
+        assert_eq!(type_source(r#""a" = "b""#).unwrap(), vec![Type::Bool]);
+        assert_eq!(type_source("'a' != 'b'").unwrap(), vec![Type::Bool]);
+    }
+
+    #[test]
+    fn string_arithmetic() {
+        let errs = type_source(r#""a" - "b""#).unwrap_err();
+
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].span(), &(0..9));
+        assert!(matches!(
+            errs[0].kind(),
+            ErrorKind::General(msg) if msg == "arithmetic is not supported on `Str`"
+        ));
+    }
+
+    #[test]
+    fn string_ordering() {
+        assert_eq!(type_source(r#""a" = "b""#).unwrap(), vec![Type::Bool]);
+
+        for input in [r#""a" < "b""#, "'a' >= 'b'", "true > false"] {
+            let errs = type_source(input).unwrap_err();
+
+            assert_eq!(errs.len(), 1, "{input:?}");
+            assert!(
+                matches!(
+                    errs[0].kind(),
+                    ErrorKind::General(msg) if msg.starts_with("ordering is not supported on")
+                ),
+                "{input:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn compound() {
+        assert_eq!(type_source("{ 1; true }").unwrap(), vec![Type::Bool]);
+        assert!(type_source("{ 1 + true; 2 }").is_err());
+    }
+
+    #[test]
+    fn if_else() {
+        assert_eq!(
+            type_source("if true { 1 } else { 2 }").unwrap(),
+            vec![Type::INT]
+        );
+        assert_eq!(type_source("if false { () }").unwrap(), vec![Type::Unit]);
+    }
+
+    #[test]
+    fn if_branch_mismatch() {
+        let errs = type_source("if true { 1 } else { false }").unwrap_err();
+
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].span(), &(21..26));
+        assert!(matches!(
+            errs[0].kind(),
+            ErrorKind::TypeMismatch {
+                expected: Type::INT,
+                found: Type::Bool
+            }
+        ));
+    }
+
+    #[test]
+    fn if_without_else_non_unit() {
+        let errs = type_source("if true { 1 }").unwrap_err();
+
+        assert!(matches!(
+            errs[0].kind(),
+            ErrorKind::TypeMismatch {
+                expected: Type::Unit,
+                found: Type::INT
+            }
+        ));
+    }
+
+    #[test]
+    fn if_condition_not_bool() {
+        let errs = type_source("if 1 { 2 } else { 3 }").unwrap_err();
+
+        assert_eq!(errs[0].span(), &(3..4));
+        assert!(matches!(
+            errs[0].kind(),
+            ErrorKind::TypeMismatch {
+                expected: Type::Bool,
+                found: Type::INT
+            }
+        ));
+    }
+
+    #[test]
+    fn tuple_index() {
+        assert_eq!(type_source("(1, true).0").unwrap(), vec![Type::INT]);
+        assert_eq!(type_source("(a: 1, b: false).1").unwrap(), vec![Type::Bool]);
+    }
+
+    #[test]
+    fn tuple_field() {
+        assert_eq!(type_source("(a: 1, b: false).b").unwrap(), vec![Type::Bool]);
+    }
+
+    #[test]
+    fn tuple_field_unknown() {
+        let errs = type_source("(a: 1, b: false).c").unwrap_err();
+
+        assert_eq!(errs[0].span(), &(0..18));
+        assert!(matches!(
+            errs[0].kind(),
+            ErrorKind::General(msg) if msg == "no field `c` on `(a: Int, b: Bool)`"
+        ));
+    }
+
+    #[test]
+    fn array_index() {
+        assert_eq!(type_source("[1, 2, 3][1]").unwrap(), vec![Type::INT]);
+        assert_eq!(type_source("[true, false][0U]").unwrap(), vec![Type::Bool]);
+    }
+
+    #[test]
+    fn array_index_not_integer() {
+        let errs = type_source("[1, 2][true]").unwrap_err();
+
+        assert_eq!(errs[0].span(), &(7..11));
+        assert!(matches!(
+            errs[0].kind(),
+            ErrorKind::TypeMismatch {
+                expected: Type::UINT,
+                found: Type::Bool
+            }
+        ));
+    }
+
+    #[test]
+    fn array_element_mismatch() {
+        let errs = type_source("[1, true]").unwrap_err();
+
+        assert_eq!(errs[0].span(), &(4..8));
+    }
+
+    #[test]
+    fn tuple_index_out_of_range() {
+        let errs = type_source("(1, true).5").unwrap_err();
 
-        ExpressionKind::VarDef { name, ty, expr } => todo!(),
-        ExpressionKind::TypeDef { name, ty } => todo!(),
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].span(), &(0..11));
+        assert!(matches!(
+            errs[0].kind(),
+            ErrorKind::General(msg) if msg == "tuple index 5 is out of range for a tuple of 2 elements"
+        ));
     }
 }